@@ -0,0 +1,85 @@
+use crate::config::Config;
+use crate::util::pkg_base_or_name;
+
+use std::fs::{create_dir_all, read_dir, remove_file, File};
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use tr::tr;
+
+// how many logs are kept for each pkgbase
+const MAX_LOGS: usize = 5;
+
+fn log_dir(config: &Config, pkgbase: &str) -> PathBuf {
+    config.state_dir.join("logs").join(pkgbase)
+}
+
+// oldest first
+fn logs(config: &Config, pkgbase: &str) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(log_dir(config, pkgbase)) else {
+        return Vec::new();
+    };
+
+    let mut logs = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "log"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect::<Vec<_>>();
+    logs.sort();
+    logs.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Picks the path for a new build log and removes old logs past the limit.
+pub fn new_log(config: &Config, pkgbase: &str, version: &str) -> Result<PathBuf> {
+    let dir = log_dir(config, pkgbase);
+    create_dir_all(&dir).with_context(|| tr!("failed to create directory: {}", dir.display()))?;
+
+    let logs = logs(config, pkgbase);
+    for log in &logs[..logs.len().saturating_sub(MAX_LOGS - 1)] {
+        match remove_file(log) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e).with_context(|| tr!("failed to remove: {}", log.display()))
+            }
+            _ => (),
+        }
+    }
+
+    let time = Local::now().format("%Y%m%d-%H%M%S");
+    Ok(dir.join(format!("{}-{}.log", version, time)))
+}
+
+fn latest_log(config: &Config, pkg: &str) -> Option<PathBuf> {
+    if let Some(log) = logs(config, pkg).pop() {
+        return Some(log);
+    }
+
+    let db = config.alpm.localdb();
+    let pkg = db.pkg(pkg).ok()?;
+    logs(config, pkg_base_or_name(pkg)).pop()
+}
+
+pub fn show_log(config: &Config) -> Result<i32> {
+    let pkg = match config.targets.as_slice() {
+        [pkg] => pkg,
+        [] => bail!(tr!("no targets specified (use -h for help)")),
+        _ => bail!(tr!("too many arguments")),
+    };
+
+    let Some(log) = latest_log(config, pkg) else {
+        bail!(tr!("no build log for '{}'", pkg));
+    };
+
+    let mut file =
+        File::open(&log).with_context(|| tr!("failed to read file: {}", log.display()))?;
+    let mut stdout = io::stdout().lock();
+    match io::copy(&mut file, &mut stdout) {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+            Err(e).with_context(|| tr!("failed to read file: {}", log.display()))?
+        }
+        _ => (),
+    }
+
+    Ok(0)
+}
@@ -1,5 +1,6 @@
 use crate::config::Config;
 use crate::exec;
+use crate::limit::limit_build;
 use anyhow::{Context, Result};
 use nix::unistd::{Uid, User};
 use std::ffi::OsStr;
@@ -124,11 +125,13 @@ impl Chroot {
 
     pub fn build<S: AsRef<OsStr>>(
         &self,
+        config: &Config,
         pkgbuild: &Path,
         pkgs: &[&str],
         chroot_flags: &[S],
         flags: &[&str],
         env: &[(String, String)],
+        log: Option<&Path>,
     ) -> Result<()> {
         let mut cmd = Command::new("makechrootpkg");
 
@@ -137,6 +140,10 @@ impl Chroot {
             .arg("-r")
             .arg(&self.path);
 
+        if let Some(user) = config.build_user() {
+            cmd.arg("-U").arg(user);
+        }
+
         for pkg in pkgs {
             cmd.arg("-I").arg(pkg);
         }
@@ -159,7 +166,10 @@ impl Chroot {
             }
         }
 
-        exec::command(&mut cmd)?;
-        Ok(())
+        let mut cmd = limit_build(config, cmd)?;
+        match log {
+            Some(log) => exec::command_log(&mut cmd, log),
+            None => exec::command(&mut cmd),
+        }
     }
 }
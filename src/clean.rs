@@ -9,7 +9,6 @@ use std::fs::{read_dir, remove_dir_all, remove_file, set_permissions, DirEntry};
 
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
-use std::process::Command;
 
 use alpm_utils::DbListExt;
 use anyhow::{Context, Result};
@@ -184,13 +183,13 @@ fn do_remove(config: &Config, path: &Path, rm: bool) -> Result<()> {
 }
 
 pub fn clean_untracked(config: &Config, path: &Path) -> Result<()> {
-    let mut cmd = Command::new(&config.git_bin);
+    let mut cmd = exec::build_user_command(config, &config.git_bin);
     cmd.args(&config.git_flags)
         .current_dir(path)
         .args(["restore", "-SWq", "."]);
     exec::command_output(&mut cmd)?;
 
-    let mut cmd = Command::new(&config.git_bin);
+    let mut cmd = exec::build_user_command(config, &config.git_bin);
     cmd.args(&config.git_flags)
         .current_dir(path)
         .arg("clean")
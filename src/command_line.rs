@@ -1,13 +1,15 @@
 use crate::args::{PACMAN_FLAGS, PACMAN_GLOBALS};
 use crate::config::{
-    Colors, Config, ConfigEnum, LocalRepos, Mode, Op, Sign, SortMode, YesNoAll, YesNoAllTree,
-    YesNoAsk,
+    Colors, Config, ConfigEnum, LintPolicy, LocalRepos, Mode, Op, PlanFormat, ReproPolicy, Resume,
+    Sign, SortMode, YesNoAll, YesNoAllTree, YesNoAsk,
 };
 
 use std::fmt;
+use std::path::PathBuf;
 
 use anyhow::{anyhow, bail, Context, Result};
-use globset::Glob;
+use globset::{Glob, GlobSetBuilder};
+use regex::Regex;
 use tr::tr;
 use url::Url;
 
@@ -181,18 +183,29 @@ impl Config {
             Arg::Long("sudo") => self.sudo_bin = value?.to_string(),
             Arg::Long("pkgctl") => self.pkgctl_bin = value?.to_string(),
             Arg::Long("bat") => self.bat_bin = value?.to_string(),
+            Arg::Long("linter") => self.lint_bin = value?.to_string(),
             Arg::Long("fm") => self.fm = Some(value?.to_string()),
             Arg::Long("pager") => self.pager_cmd = Some(value?.to_string()),
             Arg::Long("config") => self.pacman_conf = Some(value?.to_string()),
 
             Arg::Long("builddir") | Arg::Long("clonedir") => self.build_dir = value?.into(),
             Arg::Long("develfile") => self.devel_path = value?.into(),
+            Arg::Long("upstreamfile") => self.upstream_path = value?.into(),
+            Arg::Long("statedir") => {
+                let state_dir = PathBuf::from(value?);
+                // the devel file lives in the state dir unless it was set on its own
+                if self.devel_path == self.state_dir.join("devel.toml") {
+                    self.devel_path = state_dir.join("devel.toml");
+                }
+                self.state_dir = state_dir;
+            }
             Arg::Long("makepkgconf") => self.makepkg_conf = Some(value?.to_string()),
             Arg::Long("mflags") => self.mflags.extend(split_whitespace(value?)),
             Arg::Long("gitflags") => self.git_flags.extend(split_whitespace(value?)),
             Arg::Long("gpgflags") => self.gpg_flags.extend(split_whitespace(value?)),
             Arg::Long("sudoflags") => self.sudo_flags.extend(split_whitespace(value?)),
             Arg::Long("batflags") => self.bat_flags.extend(split_whitespace(value?)),
+            Arg::Long("linterflags") => self.lint_flags.extend(split_whitespace(value?)),
             Arg::Long("fmflags") => self.fm_flags.extend(split_whitespace(value?)),
             Arg::Long("chrootflags") => self.chroot_flags.extend(split_whitespace(value?)),
             Arg::Long("chrootpkgs") => self
@@ -214,6 +227,11 @@ impl Config {
             Arg::Long("sortby") => self.sort_by = ConfigEnum::from_str(argkey, value?)?,
             Arg::Long("searchby") => self.search_by = ConfigEnum::from_str(argkey, value?)?,
             Arg::Long("limit") => self.limit = value?.parse()?,
+            Arg::Long("buildjobs") => self.build_jobs = value?.parse()?,
+            Arg::Long("buildmemorymax") => self.build_memory_max = Some(value?.parse()?),
+            Arg::Long("buildcpuquota") => self.build_cpu_quota = Some(value?.parse()?),
+            Arg::Long("buildnice") => self.build_nice = Some(value?.parse()?),
+            Arg::Long("builduser") => self.build_user = Some(value?.to_string()),
             Arg::Long("news") | Arg::Short('w') => self.news += 1,
             Arg::Long("stats") => self.stats = true,
             Arg::Short('s') => {
@@ -221,6 +239,9 @@ impl Config {
                 self.ssh = true;
             }
             Arg::Long("order") => self.order = true,
+            Arg::Long("print-plan") => {
+                self.print_plan = Some(PlanFormat::Json.default_or(argkey, value.ok())?)
+            }
             Arg::Short('o') => {
                 self.order = true;
                 self.optional = true;
@@ -258,6 +279,21 @@ impl Config {
             Arg::Long("skipreview") => self.skip_review = true,
             Arg::Long("review") => self.skip_review = false,
             Arg::Long("gendb") => self.gendb = true,
+            Arg::Long("fromclones") => self.from_clones = true,
+            Arg::Long("develstatus") => self.devel_status = true,
+            Arg::Long("resume") => {
+                self.resume = Some(Resume::Continue.default_or(argkey, value.ok())?)
+            }
+            Arg::Long("rollback") => self.rollback = Some(value.ok().map_or(Ok(1), |n| n.parse())?),
+            Arg::Long("log") => self.show_log = true,
+            Arg::Long("providers") => self.list_providers = true,
+            Arg::Long("resetproviders") => self.reset_providers = true,
+            Arg::Long("retry-failed") => self.retry_failed = true,
+            Arg::Long("writelock") => self.write_lock = Some(value?.into()),
+            Arg::Long("locked") => self.locked = Some(value?.into()),
+            Arg::Long("apply") => self.apply = Some(value?.into()),
+            Arg::Long("export") => self.export = Some(value?.into()),
+            Arg::Long("import") => self.import = Some(value?.into()),
             Arg::Long("nocheck") => self.no_check = true,
             Arg::Long("devel") => self.devel = true,
             Arg::Long("nodevel") => self.devel = false,
@@ -306,8 +342,16 @@ impl Config {
             Arg::Long("ssh") => self.ssh = true,
             Arg::Long("failfast") => self.fail_fast = true,
             Arg::Long("nofailfast") => self.fail_fast = false,
+            Arg::Long("lint") => self.lint = Some(LintPolicy::Warn.default_or(argkey, value.ok())?),
+            Arg::Long("nolint") => self.lint = None,
+            Arg::Long("verify-repro") => {
+                self.verify_repro = Some(ReproPolicy::Warn.default_or(argkey, value.ok())?)
+            }
+            Arg::Long("noverify-repro") => self.verify_repro = None,
             Arg::Long("keepsrc") => self.keep_src = true,
             Arg::Long("nokeepsrc") => self.keep_src = false,
+            Arg::Long("buildlog") => self.build_log = true,
+            Arg::Long("nobuildlog") => self.build_log = false,
             // ops
             Arg::Long("database") | Arg::Short('D') => set_op(Op::Database),
             Arg::Long("files") | Arg::Short('F') => set_op(Op::Files),
@@ -341,6 +385,16 @@ impl Config {
                     self.ignore_devel_builder.add(Glob::new(word)?);
                 }
             }
+            Arg::Long("develtags") => {
+                for word in value.unwrap_or("*").split(',') {
+                    self.devel_tags_builder.add(Glob::new(word)?);
+                }
+            }
+            Arg::Long("nodeveltags") => self.devel_tags_builder = GlobSetBuilder::new(),
+            Arg::Long("develtagfilter") => self.devel_tag_filter = Some(Regex::new(value?)?),
+            Arg::Long("develjobs") => self.devel_jobs = value?.parse()?,
+            Arg::Long("develhostjobs") => self.devel_host_jobs = value?.parse()?,
+            Arg::Long("develcachetime") => self.devel_cache_time = value?.parse()?,
             Arg::Long("assume-installed") => self.assume_installed.push(value?.to_string()),
             Arg::Long("arch") => self.arch = Some(value?.to_string()),
             Arg::Long("color") => self.color = Colors::from(value.unwrap_or("always")),
@@ -412,6 +466,15 @@ fn takes_value(arg: Arg) -> TakesValue {
         Arg::Long("gpgflags") => TakesValue::Required,
         Arg::Long("sudoflags") => TakesValue::Required,
         Arg::Long("batflags") => TakesValue::Required,
+        Arg::Long("linter") => TakesValue::Required,
+        Arg::Long("linterflags") => TakesValue::Required,
+        Arg::Long("lint") => TakesValue::Optional,
+        Arg::Long("writelock") => TakesValue::Required,
+        Arg::Long("locked") => TakesValue::Required,
+        Arg::Long("apply") => TakesValue::Required,
+        Arg::Long("export") => TakesValue::Required,
+        Arg::Long("import") => TakesValue::Required,
+        Arg::Long("verify-repro") => TakesValue::Optional,
         Arg::Long("fmflags") => TakesValue::Required,
         Arg::Long("chrootflags") => TakesValue::Required,
         Arg::Long("chrootpkgs") => TakesValue::Required,
@@ -420,6 +483,11 @@ fn takes_value(arg: Arg) -> TakesValue {
         Arg::Long("sortby") => TakesValue::Required,
         Arg::Long("searchby") => TakesValue::Required,
         Arg::Long("limit") => TakesValue::Required,
+        Arg::Long("buildjobs") => TakesValue::Required,
+        Arg::Long("buildmemorymax") => TakesValue::Required,
+        Arg::Long("buildcpuquota") => TakesValue::Required,
+        Arg::Long("buildnice") => TakesValue::Required,
+        Arg::Long("builduser") => TakesValue::Required,
         Arg::Long("removemake") => TakesValue::Optional,
         Arg::Long("redownload") => TakesValue::Optional,
         Arg::Long("rebuild") => TakesValue::Optional,
@@ -431,6 +499,8 @@ fn takes_value(arg: Arg) -> TakesValue {
         Arg::Long("provides") => TakesValue::Optional,
         Arg::Long("clonedir") => TakesValue::Required,
         Arg::Long("develfile") => TakesValue::Required,
+        Arg::Long("upstreamfile") => TakesValue::Required,
+        Arg::Long("statedir") => TakesValue::Required,
         //pacman
         Arg::Long("dbpath") | Arg::Short('b') => TakesValue::Required,
         Arg::Long("root") | Arg::Short('r') => TakesValue::Required,
@@ -446,8 +516,16 @@ fn takes_value(arg: Arg) -> TakesValue {
         Arg::Long("ignore") => TakesValue::Required,
         Arg::Long("ignoregroup") => TakesValue::Required,
         Arg::Long("ignoredevel") => TakesValue::Required,
+        Arg::Long("develtags") => TakesValue::Optional,
+        Arg::Long("develtagfilter") => TakesValue::Required,
+        Arg::Long("develjobs") => TakesValue::Required,
+        Arg::Long("develhostjobs") => TakesValue::Required,
+        Arg::Long("develcachetime") => TakesValue::Required,
         Arg::Long("assume-installed") => TakesValue::Required,
         Arg::Long("print-format") => TakesValue::Required,
+        Arg::Long("print-plan") => TakesValue::Optional,
+        Arg::Long("rollback") => TakesValue::Optional,
+        Arg::Long("resume") => TakesValue::Optional,
         Arg::Long("overwrite") => TakesValue::Required,
         Arg::Long("sign") => TakesValue::Optional,
         Arg::Long("signdb") => TakesValue::Optional,
@@ -3,10 +3,13 @@ use crate::devel::save_devel_info;
 use crate::exec::{self, Status};
 use crate::fmt::color_repo;
 use crate::info::get_terminal_width;
+use crate::limit::{CpuQuota, MemoryMax};
 use crate::pkgbuild::PkgbuildRepos;
+use crate::provider::Providers;
 use crate::util::{get_provider, reopen_stdin};
 use crate::{alpm_debug_enabled, help, printtr, repo};
 
+use std::collections::BTreeMap;
 use std::env::consts::ARCH;
 use std::env::{remove_var, set_var, var};
 use std::fmt;
@@ -17,6 +20,7 @@ use std::str::FromStr;
 
 use alpm::{
     AnyDownloadEvent, AnyQuestion, Depend, DownloadEvent, DownloadResult, LogLevel, Question,
+    SelectProviderQuestion,
 };
 use ansiterm::Color::{Blue, Cyan, Green, Purple, Red, Yellow};
 use ansiterm::Style;
@@ -25,6 +29,8 @@ use anyhow::{anyhow, bail, ensure, Context, Error, Result};
 use bitflags::bitflags;
 use cini::{Callback, CallbackKind, Ini};
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use nix::unistd::{Uid, User};
+use regex::Regex;
 use tr::tr;
 use url::Url;
 
@@ -290,6 +296,52 @@ impl ConfigEnum for SortMode {
         &[("bottomup", Self::BottomUp), ("topdown", Self::TopDown)];
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    Json,
+}
+
+impl ConfigEnum for PlanFormat {
+    const VALUE_LOOKUP: ConfigEnumValues<Self> = &[("json", Self::Json)];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintPolicy {
+    Warn,
+    Ask,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    Continue,
+    Abort,
+}
+
+impl ConfigEnum for Resume {
+    const VALUE_LOOKUP: ConfigEnumValues<Self> =
+        &[("continue", Self::Continue), ("abort", Self::Abort)];
+}
+
+impl ConfigEnum for LintPolicy {
+    const VALUE_LOOKUP: ConfigEnumValues<Self> = &[
+        ("warn", Self::Warn),
+        ("ask", Self::Ask),
+        ("fail", Self::Fail),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReproPolicy {
+    Warn,
+    Exclude,
+}
+
+impl ConfigEnum for ReproPolicy {
+    const VALUE_LOOKUP: ConfigEnumValues<Self> =
+        &[("warn", Self::Warn), ("exclude", Self::Exclude)];
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Mode: u32 {
@@ -413,12 +465,27 @@ pub struct Config {
     pub cache_dir: PathBuf,
     pub state_dir: PathBuf,
     pub devel_path: PathBuf,
+    pub upstream_path: PathBuf,
     pub config_path: Option<PathBuf>,
 
     pub news: u32,
     pub stats: bool,
     pub order: bool,
     pub gendb: bool,
+    pub from_clones: bool,
+    pub devel_status: bool,
+    pub resume: Option<Resume>,
+    pub rollback: Option<usize>,
+    pub show_log: bool,
+    pub list_providers: bool,
+    pub reset_providers: bool,
+    pub retry_failed: bool,
+    pub write_lock: Option<PathBuf>,
+    pub locked: Option<PathBuf>,
+    pub apply: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+    pub import: Option<PathBuf>,
+    pub print_plan: Option<PlanFormat>,
 
     #[default(YesNoAll::No)]
     pub redownload: YesNoAll,
@@ -465,12 +532,25 @@ pub struct Config {
     pub ssh: bool,
     pub keep_repo_cache: bool,
     pub fail_fast: bool,
+    #[default = 1]
+    pub build_jobs: usize,
     pub keep_src: bool,
+    pub build_log: bool,
+    pub lint: Option<LintPolicy>,
+    pub verify_repro: Option<ReproPolicy>,
+    pub build_memory_max: Option<MemoryMax>,
+    pub build_cpu_quota: Option<CpuQuota>,
+    pub build_nice: Option<i32>,
+    pub build_user: Option<String>,
+    pub providers: BTreeMap<String, String>,
 
     pub sign: Sign,
     pub sign_db: Sign,
 
     pub pre_build_command: Option<String>,
+    pub post_build_command: Option<String>,
+    pub post_install_command: Option<String>,
+    pub post_transaction_command: Option<String>,
 
     #[default = "makepkg"]
     pub makepkg_bin: String,
@@ -487,6 +567,8 @@ pub struct Config {
     pub pkgctl_bin: String,
     #[default = "bat"]
     pub bat_bin: String,
+    #[default = "namcap"]
+    pub lint_bin: String,
     pub fm: Option<String>,
     pub sudo_loop: Vec<String>,
 
@@ -495,6 +577,7 @@ pub struct Config {
     pub gpg_flags: Vec<String>,
     pub sudo_flags: Vec<String>,
     pub bat_flags: Vec<String>,
+    pub lint_flags: Vec<String>,
     pub fm_flags: Vec<String>,
     pub chroot_flags: Vec<String>,
     pub pager_cmd: Option<String>,
@@ -543,6 +626,16 @@ pub struct Config {
     pub ignore_devel: GlobSet,
     #[default(GlobSetBuilder::new())]
     pub ignore_devel_builder: GlobSetBuilder,
+    #[default(GlobSet::empty())]
+    pub devel_tags: GlobSet,
+    #[default(GlobSetBuilder::new())]
+    pub devel_tags_builder: GlobSetBuilder,
+    pub devel_tag_filter: Option<Regex>,
+    #[default = 16]
+    pub devel_jobs: usize,
+    #[default = 4]
+    pub devel_host_jobs: usize,
+    pub devel_cache_time: u64,
     pub assume_installed: Vec<String>,
 
     #[default(PkgbuildRepos::new(aur_fetch::Fetch::with_cache_dir("repo")))]
@@ -593,6 +686,7 @@ impl Config {
         let old_devel_path = state.join("devel.json");
         let devel_path = state.join("devel.toml");
         let config_path = config.join("paru.conf");
+        let upstream_path = config.join("upstream.toml");
 
         let old = if old_devel_path.exists() {
             Some(&old_devel_path)
@@ -615,6 +709,7 @@ impl Config {
             cache_dir,
             state_dir,
             devel_path,
+            upstream_path,
             ..Self::default()
         };
 
@@ -756,17 +851,28 @@ impl Config {
             self.aur_url.clone()
         };
 
+        if let Some(name) = self.build_user() {
+            let user = User::from_name(name)?.with_context(|| tr!("unknown user: {}", name))?;
+            // the default is under root's home which the build user can't reach,
+            // so use the build user's default instead
+            if self.build_dir == self.cache_dir.join("clone") {
+                self.build_dir = user.dir.join(".cache/paru/clone");
+            }
+        }
+
+        let (git, git_flags) = self.clone_git();
         self.fetch = aur_fetch::Fetch {
-            git: self.git_bin.clone().into(),
-            git_flags: self.git_flags.clone(),
+            git: git.into(),
+            git_flags,
             clone_dir: self.build_dir.clone(),
             diff_dir: self.cache_dir.join("diff"),
             aur_url: aur_url.clone(),
         };
 
+        let (git, git_flags) = self.clone_git();
         self.pkgbuild_repos.fetch = aur_fetch::Fetch {
-            git: self.git_bin.clone().into(),
-            git_flags: self.git_flags.clone(),
+            git: git.into(),
+            git_flags,
             clone_dir: self.build_dir.join("repo"),
             diff_dir: self.cache_dir.join("repo/diff"),
             aur_url,
@@ -827,6 +933,7 @@ then initialise it with:
 
         self.no_warn = self.no_warn_builder.build()?;
         self.ignore_devel = self.ignore_devel_builder.build()?;
+        self.devel_tags = self.devel_tags_builder.build()?;
 
         if !self.assume_installed.is_empty() && !self.chroot {
             self.mflags.push("-d".to_string());
@@ -869,7 +976,10 @@ then initialise it with:
                 )
             })?;
 
-        alpm.set_question_cb((self.no_confirm, self.color), question);
+        alpm.set_question_cb(
+            (self.no_confirm, self.color, Providers::new(self)),
+            question,
+        );
         alpm.set_dl_cb((), download);
         alpm.set_log_cb(self.color, log);
         alpm_utils::configure_alpm(&mut alpm, &self.pacman)?;
@@ -1016,15 +1126,20 @@ then initialise it with:
             "Sudo" => self.sudo_bin = value,
             "Pager" => self.pager_cmd = Some(value),
             "Bat" => self.bat_bin = value,
+            "Linter" => self.lint_bin = value,
             "FileManager" => self.fm = Some(value),
             "MFlags" => self.mflags.extend(split),
             "GitFlags" => self.git_flags.extend(split),
             "GpgFlags" => self.gpg_flags.extend(split),
             "SudoFlags" => self.sudo_flags.extend(split),
             "BatFlags" => self.bat_flags.extend(split),
+            "LinterFlags" => self.lint_flags.extend(split),
             "FileManagerFlags" => self.fm_flags.extend(split),
             "ChrootFlags" => self.chroot_flags.extend(split),
             "PreBuildCommand" => self.pre_build_command = Some(value),
+            "PostBuildCommand" => self.post_build_command = Some(value),
+            "PostInstallCommand" => self.post_install_command = Some(value),
+            "PostTransactionCommand" => self.post_transaction_command = Some(value),
             _ => eprintln!(
                 "{}",
                 tr!("error: unknown option '{}' in section [bin]", key)
@@ -1066,6 +1181,11 @@ then initialise it with:
             "Rebuild" => self.rebuild = YesNoAllTree::Yes.default_or(key, value)?,
             "RemoveMake" => self.remove_make = YesNoAsk::Yes.default_or(key, value)?,
             "UpgradeMenu" => self.upgrade_menu = true,
+            "DevelTags" => {
+                for word in value.unwrap_or("*").split_whitespace() {
+                    self.devel_tags_builder.add(Glob::new(word)?);
+                }
+            }
             "LocalRepo" => self.repos = LocalRepos::new(value),
             "Chroot" => {
                 self.chroot = true;
@@ -1082,6 +1202,9 @@ then initialise it with:
             "KeepRepoCache" => self.keep_repo_cache = true,
             "FailFast" => self.fail_fast = true,
             "KeepSrc" => self.keep_src = true,
+            "BuildLog" => self.build_log = true,
+            "Lint" => self.lint = Some(LintPolicy::Warn.default_or(key, value)?),
+            "VerifyRepro" => self.verify_repro = Some(ReproPolicy::Warn.default_or(key, value)?),
             "SignDb" => {
                 self.sign_db = match value {
                     Some(v) => Sign::Key(v.to_string()),
@@ -1115,6 +1238,18 @@ then initialise it with:
             "SortBy" => self.sort_by = ConfigEnum::from_str(key, value?.as_str())?,
             "SearchBy" => self.search_by = ConfigEnum::from_str(key, value?.as_str())?,
             "Limit" => self.limit = value?.parse()?,
+            "BuildJobs" => self.build_jobs = value?.parse()?,
+            "BuildMemoryMax" => self.build_memory_max = Some(value?.parse()?),
+            "BuildCPUQuota" => self.build_cpu_quota = Some(value?.parse()?),
+            "BuildNice" => self.build_nice = Some(value?.parse()?),
+            "BuildUser" => self.build_user = Some(value?),
+            "Provider" => {
+                let value = value?;
+                let (dep, pkg) = value
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!(tr!("invalid value for '{}': {}", key, value)))?;
+                self.providers.insert(dep.to_string(), pkg.to_string());
+            }
             "CompletionInterval" => self.completion_interval = value?.parse()?,
             "PacmanConf" => self.pacman_conf = Some(value?),
             "MakepkgConf" => self.makepkg_conf = Some(value?),
@@ -1131,6 +1266,10 @@ then initialise it with:
                     self.ignore_devel_builder.add(Glob::new(word)?);
                 }
             }
+            "DevelTagFilter" => self.devel_tag_filter = Some(Regex::new(&value?)?),
+            "DevelJobs" => self.devel_jobs = value?.parse()?,
+            "DevelHostJobs" => self.devel_host_jobs = value?.parse()?,
+            "DevelCacheTime" => self.devel_cache_time = value?.parse()?,
             "NoWarn" => {
                 for word in value?.split_whitespace() {
                     self.no_warn_builder.add(Glob::new(word)?);
@@ -1158,6 +1297,27 @@ then initialise it with:
         Ok(())
     }
 
+    /// The user builds run as, only set when paru is run as root.
+    pub fn build_user(&self) -> Option<&str> {
+        self.build_user
+            .as_deref()
+            .filter(|_| Uid::current().is_root())
+    }
+
+    /// The git command and flags that write the clones. Git refuses to work
+    /// in clones owned by another user so it runs as the build user if set.
+    pub fn clone_git(&self) -> (String, Vec<String>) {
+        match self.build_user() {
+            Some(user) => {
+                let mut flags = vec!["-u".to_string(), user.to_string(), "--".to_string()];
+                flags.push(self.git_bin.clone());
+                flags.extend(self.git_flags.iter().cloned());
+                ("runuser".to_string(), flags)
+            }
+            None => (self.git_bin.clone(), self.git_flags.clone()),
+        }
+    }
+
     pub fn aur_namespace(&self) -> &str {
         if self.pacman.repos.iter().any(|r| r.name == "aur") {
             // hack for search install
@@ -1176,37 +1336,16 @@ pub fn version() {
     println!(" - libalpm v{}", alpm::version());
 }
 
-fn question(question: AnyQuestion, (no_confirm, c): &mut (bool, Colors)) {
+fn question(question: AnyQuestion, (no_confirm, c, providers): &mut (bool, Colors, Providers)) {
     match question.question() {
         Question::SelectProvider(mut question) => {
-            let providers = question.providers();
-            let len = providers.len();
-
-            println!();
-            let prompt = tr!(
-                "There are {n} providers available for {pkg}:",
-                n = len,
-                pkg = question.depend()
-            );
-            print!("{} {}", c.action.paint("::"), c.bold.paint(prompt));
-
-            let mut db = String::new();
-            for (n, pkg) in providers.iter().enumerate() {
-                let pkg_db = pkg.db().unwrap();
-                if pkg_db.name() != db {
-                    db = pkg_db.name().to_string();
-                    println!(
-                        "\n{} {} {}:",
-                        c.action.paint("::"),
-                        c.bold.paint(tr!("Repository")),
-                        color_repo(c.enabled, pkg_db.name())
-                    );
-                    print!("    ");
-                }
-                print!("{}) {}  ", n + 1, pkg.name());
-            }
-
-            let index = get_provider(len, *no_confirm);
+            let dep = question.depend().to_string();
+            let names = question
+                .providers()
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>();
+            let index = providers.choose(&dep, &names, || ask_provider(&question, *no_confirm, c));
             question.set_index(index as i32);
         }
         Question::InstallIgnorepkg(mut question) => {
@@ -1216,6 +1355,37 @@ fn question(question: AnyQuestion, (no_confirm, c): &mut (bool, Colors)) {
     }
 }
 
+fn ask_provider(question: &SelectProviderQuestion, no_confirm: bool, c: &Colors) -> usize {
+    let providers = question.providers();
+    let len = providers.len();
+
+    println!();
+    let prompt = tr!(
+        "There are {n} providers available for {pkg}:",
+        n = len,
+        pkg = question.depend()
+    );
+    print!("{} {}", c.action.paint("::"), c.bold.paint(prompt));
+
+    let mut db = String::new();
+    for (n, pkg) in providers.iter().enumerate() {
+        let pkg_db = pkg.db().unwrap();
+        if pkg_db.name() != db {
+            db = pkg_db.name().to_string();
+            println!(
+                "\n{} {} {}:",
+                c.action.paint("::"),
+                c.bold.paint(tr!("Repository")),
+                color_repo(c.enabled, pkg_db.name())
+            );
+            print!("    ");
+        }
+        print!("{}) {}  ", n + 1, pkg.name());
+    }
+
+    get_provider(len, no_confirm)
+}
+
 fn download(filename: &str, event: AnyDownloadEvent, _: &mut ()) {
     if filename.ends_with(".sig") {
         return;
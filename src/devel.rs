@@ -1,8 +1,10 @@
 use crate::config::{Config, LocalRepos};
 use crate::download::{self, cache_info_with_warnings, Bases};
+use crate::fmt::{date, opt};
+use crate::info;
 use crate::print_error;
 use crate::repo;
-use crate::util::{pkg_base_or_name, split_repo_aur_pkgs};
+use crate::util::{pkg_base_or_name, save_state, split_repo_aur_pkgs};
 
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
@@ -11,31 +13,254 @@ use std::fs::{create_dir_all, read_to_string, OpenOptions};
 use std::hash::{Hash, Hasher};
 use std::io::Write;
 use std::iter::FromIterator;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use alpm::vercmp;
 use alpm_utils::{DbListExt, Target};
-use ansiterm::Style;
 use anyhow::{anyhow, bail, Context, Result};
 use aur_depends::Base;
+use chrono::Utc;
 use futures::future::{join_all, select_ok, FutureExt};
 use log::debug;
 use raur::{Cache, Raur};
+use regex::Regex;
+use rss::Channel;
 use serde::{Deserialize, Serialize, Serializer};
 use srcinfo::Srcinfo;
 use tokio::process::Command as AsyncCommand;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
 use tokio::time::timeout;
 use tr::tr;
+use unicode_width::UnicodeWidthStr;
+use url::Url;
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct _PkgInfo {
     pub repos: HashSet<RepoInfo>,
 }
 
+#[derive(Serialize, Deserialize, SmartDefault, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Vcs {
+    #[default]
+    Git,
+    Hg,
+    Svn,
+    Bzr,
+    Fossil,
+}
+
+impl Vcs {
+    fn from_scheme(scheme: &str) -> Option<Self> {
+        let vcs = scheme.split_once('+').map_or(scheme, |(vcs, _)| vcs);
+
+        match vcs {
+            "git" => Some(Vcs::Git),
+            "hg" => Some(Vcs::Hg),
+            "svn" => Some(Vcs::Svn),
+            "bzr" => Some(Vcs::Bzr),
+            "fossil" => Some(Vcs::Fossil),
+            _ => None,
+        }
+    }
+
+    fn is_git(&self) -> bool {
+        *self == Vcs::Git
+    }
+}
+
 #[derive(Serialize, Deserialize, SmartDefault, Debug, Eq, Clone)]
 pub struct RepoInfo {
     pub url: String,
     pub branch: Option<String>,
     pub commit: String,
+    #[serde(default, skip_serializing_if = "Vcs::is_git")]
+    pub vcs: Vcs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug)]
+struct Source<'a> {
+    remote: String,
+    vcs: Vcs,
+    branch: Option<&'a str>,
+    tag: Option<&'a str>,
+}
+
+#[derive(Debug)]
+struct Revision {
+    commit: String,
+    tag: Option<String>,
+}
+
+// The outcome of the last queries of a remote. Kept out of devel.toml so looking
+// for updates never writes to it.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct CheckStatus {
+    last_checked: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_seen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    failures: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedRevision {
+    commit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    time: i64,
+}
+
+// Limits how many remotes are queried at once and caches the results
+#[derive(Debug)]
+struct Remotes {
+    jobs: Semaphore,
+    host_jobs: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+    cache: Mutex<BTreeMap<String, CachedRevision>>,
+    status: Mutex<BTreeMap<String, CheckStatus>>,
+    use_cache: bool,
+}
+
+impl Remotes {
+    fn new(config: &Config, use_cache: bool) -> Self {
+        let permits = |n| if n == 0 { Semaphore::MAX_PERMITS } else { n };
+        let cache = if config.devel_cache_time != 0 {
+            Self::load(config).unwrap_or_default()
+        } else {
+            BTreeMap::new()
+        };
+
+        Remotes {
+            jobs: Semaphore::new(permits(config.devel_jobs)),
+            host_jobs: permits(config.devel_host_jobs),
+            hosts: Mutex::new(HashMap::new()),
+            cache: Mutex::new(cache),
+            status: Mutex::new(load_check_status(config)),
+            use_cache,
+        }
+    }
+
+    fn load(config: &Config) -> Option<BTreeMap<String, CachedRevision>> {
+        let file = read_to_string(config.state_dir.join(CACHE)).ok()?;
+        BTreeMap::deserialize(toml::Deserializer::parse(&file).ok()?).ok()
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        if config.devel_cache_time == 0 {
+            return Ok(());
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        let now = Utc::now().timestamp();
+        cache.retain(|_, rev| !expired(config, now, rev));
+        save_state(&config.state_dir, CACHE, &*cache)
+    }
+
+    // only called when updating so that listing updates doesn't write it
+    fn save_status(&self, config: &Config) -> Result<()> {
+        save_state(
+            &config.state_dir,
+            CHECK_STATUS,
+            &*self.status.lock().unwrap(),
+        )
+    }
+
+    fn record(&self, key: &str, rev: &Result<Revision>) {
+        let mut status = self.status.lock().unwrap();
+        let status = status.entry(key.to_string()).or_default();
+        status.last_checked = Utc::now().timestamp();
+
+        match rev {
+            Ok(rev) => {
+                status.last_seen = Some(rev.commit.clone());
+                status.last_error = None;
+                status.failures = 0;
+            }
+            Err(err) => {
+                status.last_error = Some(format!("{:#}", err).trim().to_string());
+                status.failures += 1;
+            }
+        }
+    }
+
+    fn key(remote: &str, branch: Option<&str>, tags: bool) -> String {
+        match branch {
+            _ if tags => format!("{}#tags", remote),
+            Some(branch) => format!("{}#branch={}", remote, branch),
+            None => remote.to_string(),
+        }
+    }
+
+    fn get(&self, config: &Config, key: &str) -> Option<Revision> {
+        if !self.use_cache || config.devel_cache_time == 0 {
+            return None;
+        }
+
+        let cache = self.cache.lock().unwrap();
+        let rev = cache.get(key)?;
+        if expired(config, Utc::now().timestamp(), rev) {
+            return None;
+        }
+
+        Some(Revision {
+            commit: rev.commit.clone(),
+            tag: rev.tag.clone(),
+        })
+    }
+
+    fn insert(&self, key: String, rev: &Revision) {
+        let rev = CachedRevision {
+            commit: rev.commit.clone(),
+            tag: rev.tag.clone(),
+            time: Utc::now().timestamp(),
+        };
+        self.cache.lock().unwrap().insert(key, rev);
+    }
+
+    async fn acquire(&self, remote: &str) -> Result<(OwnedSemaphorePermit, SemaphorePermit<'_>)> {
+        let host = Url::parse(remote)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_else(|| remote.to_string());
+
+        let host = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.host_jobs)))
+            .clone();
+
+        // take the host permit first so waiting on a busy host doesn't block other hosts
+        let host = host.acquire_owned().await?;
+        let job = self.jobs.acquire().await?;
+        Ok((host, job))
+    }
+}
+
+const CACHE: &str = "devel-cache.toml";
+const CHECK_STATUS: &str = "devel-status.toml";
+
+fn load_check_status(config: &Config) -> BTreeMap<String, CheckStatus> {
+    read_to_string(config.state_dir.join(CHECK_STATUS))
+        .ok()
+        .and_then(|file| BTreeMap::deserialize(toml::Deserializer::parse(&file).ok()?).ok())
+        .unwrap_or_default()
+}
+
+fn expired(config: &Config, now: i64, rev: &CachedRevision) -> bool {
+    now < rev.time || (now - rev.time) as u64 >= config.devel_cache_time
 }
 
 impl Hash for RepoInfo {
@@ -125,10 +350,26 @@ pub async fn gendb(config: &mut Config) -> Result<()> {
         !devel_info.info.contains_key(pkg)
     });
 
-    let (_pkgbuilds, aur): (Vec<_>, Vec<_>) = aur
+    if config.from_clones {
+        return gendb_from_clones(config, &aur, devel_info).await;
+    }
+
+    let (pkgbuilds, aur): (Vec<_>, Vec<_>) = aur
         .into_iter()
         .partition(|aur| config.pkgbuild_repos.pkg(config, aur).is_some());
 
+    // key by the installed pkgbase so renamed bases still match the local packages
+    let mut pkgbuild_srcinfos: Vec<(String, Srcinfo)> = Vec::new();
+    for pkg in pkgbuilds {
+        let base = pkg_base_or_name(db.pkg(pkg).unwrap());
+        if pkgbuild_srcinfos.iter().any(|(b, _)| b == base) {
+            continue;
+        }
+        if let Some((repo_pkg, _)) = config.pkgbuild_repos.pkg(config, pkg) {
+            pkgbuild_srcinfos.push((base.to_string(), repo_pkg.srcinfo.clone()));
+        }
+    }
+
     if !aur.is_empty() {
         println!(
             "{} {}",
@@ -193,7 +434,12 @@ pub async fn gendb(config: &mut Config) -> Result<()> {
         }
     }
 
-    let bases = bases.bases.into_iter().map(Base::Aur).collect::<Vec<_>>();
+    let mut pkgs = bases
+        .bases
+        .iter()
+        .filter_map(|b| Some((b.package_base(), srcinfos.get(b.package_base())?)))
+        .collect::<Vec<_>>();
+    pkgs.extend(pkgbuild_srcinfos.iter().map(|(b, s)| (b.as_str(), s)));
 
     println!(
         "{} {}",
@@ -201,7 +447,7 @@ pub async fn gendb(config: &mut Config) -> Result<()> {
         bold.paint(tr!("Looking for devel repos..."))
     );
 
-    let new_devel_info = fetch_devel_info(config, &bases, &srcinfos).await?;
+    let new_devel_info = fetch_srcinfo_devel_info(config, &pkgs).await?;
 
     for (k, v) in new_devel_info.info {
         devel_info.info.entry(k).or_insert(v);
@@ -212,6 +458,235 @@ pub async fn gendb(config: &mut Config) -> Result<()> {
     Ok(())
 }
 
+async fn gendb_from_clones(
+    config: &Config,
+    pkgs: &[&str],
+    mut devel_info: DevelInfo,
+) -> Result<()> {
+    let action = config.color.action;
+    let bold = config.color.bold;
+    let db = config.alpm.localdb();
+    let mut bases = Vec::new();
+
+    for &pkg in pkgs {
+        let base = pkg_base_or_name(db.pkg(pkg).unwrap());
+        if bases.iter().any(|(b, _, _)| *b == base) {
+            continue;
+        }
+
+        let (dir, srcinfo) = match config.pkgbuild_repos.pkg(config, pkg) {
+            Some((repo_pkg, _)) => (repo_pkg.path.clone(), repo_pkg.srcinfo.clone()),
+            None => {
+                let dir = config.build_dir.join(base);
+                let path = dir.join(".SRCINFO");
+                if !path.exists() {
+                    debug!("no clone for {}", base);
+                    continue;
+                }
+                match Srcinfo::from_path(path) {
+                    Ok(srcinfo) => (dir, srcinfo),
+                    Err(err) => {
+                        let err =
+                            anyhow!(err).context(tr!("failed to parse srcinfo for '{}'", base));
+                        print_error(config.color.error, err);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        bases.push((base, dir, srcinfo));
+    }
+
+    println!(
+        "{} {}",
+        action.paint("::"),
+        bold.paint(tr!("Reading devel repos from clones..."))
+    );
+
+    let mut parsed = Vec::new();
+    let mut futures = Vec::new();
+    let arch = config.alpm.architectures().first().unwrap_or_default();
+
+    for (pkgbase, dir, srcinfo) in &bases {
+        let tags = config.devel_tags.is_match(pkgbase);
+        for url in srcinfo.base.source.arch(arch) {
+            let (source, name) = match (parse_url(url), source_dir(url)) {
+                (Some(source), Some(name)) => (source, name),
+                _ => continue,
+            };
+            if source.tag.is_some() && !tags {
+                continue;
+            }
+
+            let checkout = dir.join("src").join(name);
+            if !checkout.is_dir() {
+                debug!("no checkout for {} at {}", pkgbase, checkout.display());
+                continue;
+            }
+
+            futures.push(local_revision(config, source.vcs, checkout));
+            parsed.push((source, *pkgbase));
+        }
+    }
+
+    let commits = join_all(futures).await;
+    for ((source, pkgbase), commit) in parsed.into_iter().zip(commits) {
+        match commit {
+            Err(e) => print_error(
+                config.color.error,
+                e.context(tr!("failed to read revision: {}", pkgbase)),
+            ),
+            Ok(commit) => {
+                let url_info = RepoInfo {
+                    url: source.remote,
+                    branch: source.branch.map(|s| s.to_string()),
+                    commit,
+                    vcs: source.vcs,
+                    tag: source.tag.map(|s| s.to_string()),
+                };
+
+                devel_info
+                    .info
+                    .entry(pkgbase.to_string())
+                    .or_default()
+                    .repos
+                    .insert(url_info);
+            }
+        }
+    }
+
+    save_devel_info(config, &devel_info).context(tr!("failed to save devel info"))?;
+
+    Ok(())
+}
+
+// the directory makepkg checks a vcs source out to under src/
+fn source_dir(source: &str) -> Option<&str> {
+    if let Some((name, _)) = source.split_once("::") {
+        return Some(name);
+    }
+
+    let url = source.split_once('#').map_or(source, |(u, _)| u);
+    let url = url.split_once('?').map_or(url, |(u, _)| u);
+    let name = url.trim_end_matches('/').rsplit('/').next()?;
+    Some(name.strip_suffix(".git").unwrap_or(name))
+}
+
+// the remote and checkout directory of a git source
+pub fn git_source(source: &str) -> Option<(String, &str)> {
+    let parsed = parse_url(source)?;
+    if parsed.vcs != Vcs::Git {
+        return None;
+    }
+    Some((parsed.remote, source_dir(source)?))
+}
+
+async fn local_revision(config: &Config, vcs: Vcs, dir: PathBuf) -> Result<String> {
+    let git = &config.git_bin;
+    #[cfg(feature = "mock")]
+    let _ = git;
+    #[cfg(feature = "mock")]
+    let git = "git";
+
+    let mut command = match vcs {
+        Vcs::Git => {
+            let mut command = AsyncCommand::new(git);
+            command.args(&config.git_flags).args(["rev-parse", "HEAD"]);
+            command
+        }
+        Vcs::Hg => {
+            let mut command = AsyncCommand::new("hg");
+            command
+                .env("HGPLAIN", "1")
+                .args(["identify", "--id", "--rev", "."]);
+            command
+        }
+        Vcs::Svn => {
+            let mut command = AsyncCommand::new("svn");
+            command.args(["info", "--show-item", "last-changed-revision"]);
+            command
+        }
+        Vcs::Bzr => {
+            let mut command = AsyncCommand::new("bzr");
+            command.arg("revno");
+            command
+        }
+        Vcs::Fossil => {
+            let mut command = AsyncCommand::new("fossil");
+            command.arg("info");
+            command
+        }
+    };
+
+    debug!("reading {:?} revision in {}", vcs, dir.display());
+    command.current_dir(&dir);
+    let output = command_stdout(command).await?;
+
+    let rev = if vcs == Vcs::Fossil {
+        output
+            .lines()
+            .find_map(|l| l.strip_prefix("checkout:"))
+            .and_then(|l| l.split_whitespace().next())
+    } else {
+        output.split_whitespace().next()
+    };
+
+    let rev = rev.with_context(|| tr!("failed to parse revision for: {}", dir.display()))?;
+    Ok(rev.to_string())
+}
+
+pub fn devel_status(config: &Config) -> Result<i32> {
+    let devel_info = load_devel_info(config)?.unwrap_or_default();
+    let checks = load_check_status(config);
+    let bold = config.color.bold;
+
+    let keys = [
+        tr!("Remote"),
+        tr!("Branch"),
+        tr!("Tag"),
+        tr!("Installed Commit"),
+        tr!("Last Seen Commit"),
+        tr!("Last Checked"),
+        tr!("Last Error"),
+        tr!("Failures"),
+    ];
+    let indent = keys.iter().map(|k| k.width()).max().unwrap_or(0) + 3;
+    let print = |k: &str, v: &str| info::print(config.color, indent, config.cols, k, v);
+
+    let ordered: BTreeMap<_, _> = devel_info.info.iter().collect();
+
+    for (pkgbase, info) in ordered {
+        println!("{}", bold.paint(pkgbase));
+
+        for repo in info.repos.iter().collect::<BTreeSet<_>>() {
+            let ignored = config.ignore_devel_source.iter().any(|u| u == &repo.url);
+            let mut remote = repo.url.clone();
+            if ignored {
+                remote.push_str(&tr!(" [ignored]"));
+            }
+
+            let key = Remotes::key(&repo.url, repo.branch.as_deref(), repo.tag.is_some());
+            let check = checks.get(&key).cloned().unwrap_or_default();
+            let checked = (check.last_checked != 0).then(|| date(check.last_checked));
+
+            print(&keys[0], &remote);
+            print(&keys[1], &opt(&repo.branch));
+            if repo.tag.is_some() {
+                print(&keys[2], &opt(&repo.tag));
+            }
+            print(&keys[3], &repo.commit);
+            print(&keys[4], &opt(&check.last_seen));
+            print(&keys[5], &checked.unwrap_or_else(|| tr!("Never")));
+            print(&keys[6], &opt(&check.last_error));
+            print(&keys[7], &check.failures.to_string());
+            println!();
+        }
+    }
+
+    Ok(0)
+}
+
 pub fn save_devel_info(config: &Config, devel_info: &DevelInfo) -> Result<()> {
     create_dir_all(&config.state_dir).with_context(|| {
         tr!(
@@ -250,7 +725,16 @@ pub fn save_devel_info(config: &Config, devel_info: &DevelInfo) -> Result<()> {
     Ok(())
 }
 
-async fn ls_remote_internal(
+async fn command_stdout(mut command: AsyncCommand) -> Result<String> {
+    let output = command.output().await?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn ls_remote_git(
     git: &str,
     flags: &[String],
     remote: &str,
@@ -270,80 +754,220 @@ async fn ls_remote_internal(
         .arg(branch.unwrap_or("HEAD"));
 
     debug!("git ls-remote {} {}", remote, branch.unwrap_or("HEAD"));
-    let output = command.output().await?;
-    if !output.status.success() {
-        bail!("{}", String::from_utf8_lossy(&output.stderr));
-    }
-
-    let sha = String::from_utf8_lossy(&output.stdout)
-        .split('\t')
-        .next()
-        .unwrap()
-        .to_string();
+    let output = command_stdout(command).await?;
+    let sha = output.split('\t').next().unwrap().to_string();
 
     Ok(sha)
 }
 
-async fn ls_remote(
-    style: Style,
+async fn ls_remote_tags(
     git: &str,
     flags: &[String],
+    remote: &str,
+    filter: Option<&Regex>,
+) -> Result<Revision> {
+    #[cfg(feature = "mock")]
+    let _ = git;
+    #[cfg(feature = "mock")]
+    let git = "git";
+
+    let mut command = AsyncCommand::new(git);
+    command
+        .args(flags)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["ls-remote", "--tags", "--refs"])
+        .arg(remote);
+
+    debug!("git ls-remote --tags --refs {}", remote);
+    let output = command_stdout(command).await?;
+
+    let (commit, tag) = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(sha, r)| Some((sha, r.strip_prefix("refs/tags/")?)))
+        .filter(|(_, tag)| filter.is_none_or(|f| f.is_match(tag)))
+        .max_by(|a, b| vercmp(a.1, b.1))
+        .with_context(|| tr!("no matching tags found: {}", remote))?;
+
+    Ok(Revision {
+        commit: commit.to_string(),
+        tag: Some(tag.to_string()),
+    })
+}
+
+async fn ls_remote_hg(remote: &str, branch: Option<&str>) -> Result<String> {
+    let rev = branch.unwrap_or("default");
+    let mut command = AsyncCommand::new("hg");
+    command
+        .env("HGPLAIN", "1")
+        .args(["identify", "--id", "--rev", rev, remote]);
+
+    debug!("hg identify --id --rev {} {}", rev, remote);
+    let output = command_stdout(command).await?;
+    let id = output
+        .lines()
+        .last()
+        .and_then(|l| l.split_whitespace().next())
+        .with_context(|| tr!("failed to parse revision for: {}", remote))?;
+
+    Ok(id.to_string())
+}
+
+async fn ls_remote_svn(remote: &str) -> Result<String> {
+    let mut command = AsyncCommand::new("svn");
+    command.args([
+        "info",
+        "--non-interactive",
+        "--show-item",
+        "last-changed-revision",
+        remote,
+    ]);
+
+    debug!("svn info --show-item last-changed-revision {}", remote);
+    let output = command_stdout(command).await?;
+    Ok(output.trim().to_string())
+}
+
+async fn ls_remote_bzr(remote: &str) -> Result<String> {
+    let mut command = AsyncCommand::new("bzr");
+    command.arg("revno").arg(remote);
+
+    debug!("bzr revno {}", remote);
+    let output = command_stdout(command).await?;
+    Ok(output.trim().to_string())
+}
+
+async fn ls_remote_fossil(config: &Config, remote: &str, branch: Option<&str>) -> Result<String> {
+    // fossil can't query a remote without cloning it so ask the web ui for the latest check-in
+    let mut url = Url::parse(&format!("{}/timeline.rss", remote.trim_end_matches('/')))?;
+    url.query_pairs_mut()
+        .append_pair("y", "ci")
+        .append_pair("n", "1");
+    if let Some(branch) = branch {
+        url.query_pairs_mut().append_pair("tag", branch);
+    }
+
+    debug!("fossil timeline {}", url);
+    let resp = config.raur.client().get(url.clone()).send().await?;
+    if !resp.status().is_success() {
+        bail!("{}: {}", url, resp.status());
+    }
+    let bytes = resp.bytes().await?;
+    let channel = Channel::read_from(bytes.as_ref())?;
+
+    let checkin = channel
+        .items()
+        .first()
+        .and_then(|item| item.guid().map(|g| g.value()).or(item.link()))
+        .with_context(|| tr!("failed to parse revision for: {}", remote))?;
+    let hash = checkin.trim_end_matches('/').rsplit('/').next().unwrap();
+
+    Ok(hash.to_string())
+}
+
+async fn ls_remote_internal(
+    config: &Config,
+    vcs: Vcs,
+    remote: &str,
+    branch: Option<&str>,
+    tags: bool,
+) -> Result<Revision> {
+    let (git, flags) = (&config.git_bin, &config.git_flags);
+
+    let commit = match vcs {
+        Vcs::Git if tags => {
+            let filter = config.devel_tag_filter.as_ref();
+            return ls_remote_tags(git, flags, remote, filter).await;
+        }
+        Vcs::Git => ls_remote_git(git, flags, remote, branch).await?,
+        Vcs::Hg => ls_remote_hg(remote, branch).await?,
+        Vcs::Svn => ls_remote_svn(remote).await?,
+        Vcs::Bzr => ls_remote_bzr(remote).await?,
+        Vcs::Fossil => ls_remote_fossil(config, remote, branch).await?,
+    };
+
+    Ok(Revision { commit, tag: None })
+}
+
+async fn ls_remote(
+    config: &Config,
+    remotes: &Remotes,
+    vcs: Vcs,
     remote: String,
     branch: Option<&str>,
-) -> Result<String> {
+    tags: bool,
+) -> Result<Revision> {
     let remote = &remote;
+    let key = Remotes::key(remote, branch, tags);
+
+    if let Some(rev) = remotes.get(config, &key) {
+        debug!("devel check {}: using cached result", remote);
+        return Ok(rev);
+    }
+
+    let _permits = remotes.acquire(remote).await?;
     let time = Duration::from_secs(15);
-    let future = ls_remote_internal(git, flags, remote, branch);
+    let future = ls_remote_internal(config, vcs, remote, branch, tags);
     let future = timeout(time, future);
 
-    if let Ok(v) = future.await {
-        v
-    } else {
-        print_error(
-            style,
-            anyhow!("timed out looking for devel update: {}", remote),
-        );
-        bail!("")
-    }
+    let rev = match future.await {
+        Ok(rev) => rev,
+        Err(_) => {
+            print_error(
+                config.color.error,
+                anyhow!("timed out looking for devel update: {}", remote),
+            );
+            Err(anyhow!(tr!("timed out")))
+        }
+    };
+
+    remotes.record(&key, &rev);
+    let rev = rev?;
+    remotes.insert(key, &rev);
+    Ok(rev)
 }
 
-fn parse_url(source: &str) -> Option<(String, &'_ str, Option<&'_ str>)> {
+fn parse_url(source: &str) -> Option<Source<'_>> {
     let url = source.splitn(2, "::").last().unwrap();
 
-    if !url.starts_with("git") || !url.contains("://") {
-        return None;
-    }
-
-    let mut split = url.splitn(2, "://");
-    let protocol = split.next().unwrap();
-    let protocol = protocol.rsplit('+').next().unwrap();
-    let rest = split.next().unwrap();
+    let (scheme, rest) = url.split_once("://")?;
+    let vcs = Vcs::from_scheme(scheme)?;
+    let protocol = scheme.rsplit('+').next().unwrap();
 
     let mut split = rest.splitn(2, '#');
     let remote = split.next().unwrap();
     let remote = remote.split_once('?').map_or(remote, |(x, _)| x);
     let remote = format!("{}://{}", protocol, remote);
 
-    let branch = if let Some(fragment) = split.next() {
+    let mut source = Source {
+        remote,
+        vcs,
+        branch: None,
+        tag: None,
+    };
+
+    if let Some(fragment) = split.next() {
         let fragment = fragment.split_once('?').map_or(fragment, |(x, _)| x);
         let mut split = fragment.splitn(2, '=');
         let frag_type = split.next().unwrap();
 
         match frag_type {
-            "commit" | "tag" => return None,
-            "branch" => split.next(),
-            _ => None,
+            "tag" if vcs == Vcs::Git => source.tag = split.next(),
+            "commit" | "tag" | "revision" => return None,
+            "branch" => source.branch = split.next(),
+            _ => (),
         }
-    } else {
-        None
-    };
+    }
 
-    Some((remote, protocol, branch))
+    Some(source)
 }
 
-pub async fn possible_devel_updates(config: &Config) -> Result<Vec<String>> {
+/// Lists the devel packages with updates. When record is set the results of
+/// the checks are saved for --develstatus.
+pub async fn possible_devel_updates(config: &Config, record: bool) -> Result<Vec<String>> {
     let devel_info = load_devel_info(config)?.unwrap_or_default();
     let db = config.alpm.localdb();
+    let remotes = Remotes::new(config, true);
     let mut futures = Vec::new();
     let mut pkgbases: HashMap<&str, Vec<&alpm::Package>> = HashMap::new();
 
@@ -367,17 +991,22 @@ pub async fn possible_devel_updates(config: &Config) -> Result<Vec<String>> {
             let (_, dbs) = repo::repo_aur_dbs(config);
             for db in dbs {
                 if db.pkg(pkg.as_str()).is_ok() {
-                    futures.push(pkg_has_update(config, pkg, &repos.repos));
+                    futures.push(pkg_has_update(config, &remotes, pkg, &repos.repos));
                     continue 'outer;
                 }
             }
         } else if config.alpm.syncdbs().pkg(pkg.as_str()).is_err() {
-            futures.push(pkg_has_update(config, pkg, &repos.repos));
+            futures.push(pkg_has_update(config, &remotes, pkg, &repos.repos));
         }
     }
 
     let updates = join_all(futures).await;
 
+    remotes.save(config)?;
+    if record {
+        remotes.save_status(config)?;
+    }
+
     let mut updates = updates
         .into_iter()
         .flatten()
@@ -400,15 +1029,6 @@ pub async fn filter_devel_updates(
     let mut custom = Vec::new();
     let db = config.alpm.localdb();
 
-    'pkg: for update in updates {
-        if let Some((base, pkg)) = config.pkgbuild_repos.pkg(config, update) {
-            custom.push(Target::new(Some(base.repo.clone()), pkg.pkgname.clone()));
-            continue 'pkg;
-        }
-
-        aur.push(update);
-    }
-
     let (_, dbs) = repo::repo_aur_dbs(config);
     for pkg in dbs.iter().flat_map(|d| d.pkgs()) {
         let name = pkg_base_or_name(pkg);
@@ -420,11 +1040,39 @@ pub async fn filter_devel_updates(
         pkgbases.entry(name).or_default().push(pkg);
     }
 
-    config.raur.cache_info(cache, &aur).await?;
-    let aur = aur
+    for update in updates {
+        let pkgs = match pkgbases.remove(update.as_str()) {
+            Some(pkgs) => pkgs,
+            None => continue,
+        };
+
+        // the pkgbase may have been renamed or split in the pkgbuild repo so look up
+        // each installed package instead of the base
+        let pkgbuilds = pkgs
+            .iter()
+            .filter_map(|p| config.pkgbuild_repos.pkg(config, p.name()).map(|r| (p, r)))
+            .collect::<Vec<_>>();
+
+        if pkgbuilds.is_empty() {
+            aur.push(pkgs);
+            continue;
+        }
+
+        let pkgbuilds = pkgbuilds
+            .into_iter()
+            .filter(|(p, _)| !p.should_ignore())
+            .filter(|(p, _)| !config.ignore_devel.is_match(p.name()))
+            .map(|(_, (base, pkg))| Target::new(Some(base.repo.clone()), pkg.pkgname.clone()));
+
+        custom.extend(pkgbuilds);
+    }
+
+    let names = aur
         .iter()
-        .map(|u| pkgbases.remove(u.as_str()).unwrap())
+        .flatten()
+        .map(|p| p.name().to_string())
         .collect::<Vec<_>>();
+    config.raur.cache_info(cache, &names).await?;
 
     let mut updates = Vec::new();
 
@@ -447,24 +1095,23 @@ pub async fn filter_devel_updates(
     Ok(updates)
 }
 
-pub async fn pkg_has_update<'pkg>(
+async fn pkg_has_update<'pkg>(
     config: &'_ Config,
+    remotes: &'_ Remotes,
     pkg: &'pkg str,
     info: &'_ HashSet<RepoInfo>,
 ) -> Option<&'pkg str> {
-    if info.is_empty() {
-        return None;
-    }
-
     let mut futures = Vec::with_capacity(info.len());
 
     for info in info {
         if config.ignore_devel_source.iter().any(|u| u == &info.url) {
             continue;
         }
+        if info.tag.is_some() && !config.devel_tags.is_match(pkg) {
+            continue;
+        }
 
-        futures
-            .push(has_update(config.color.error, &config.git_bin, &config.git_flags, info).boxed());
+        futures.push(has_update(config, remotes, info).boxed());
     }
 
     if !futures.is_empty() && select_ok(futures).await.is_ok() {
@@ -474,16 +1121,34 @@ pub async fn pkg_has_update<'pkg>(
     }
 }
 
-async fn has_update(style: Style, git: &str, flags: &[String], url: &RepoInfo) -> Result<()> {
-    let sha = ls_remote(style, git, flags, url.url.clone(), url.branch.as_deref()).await?;
+async fn has_update(config: &Config, remotes: &Remotes, url: &RepoInfo) -> Result<()> {
+    let branch = url.branch.as_deref();
+    let tags = url.tag.is_some();
+    let rev = ls_remote(config, remotes, url.vcs, url.url.clone(), branch, tags).await?;
+
+    // the commit recorded for a pinned source is the newest tag at build time, so a
+    // pinned tag that is already behind only counts as an update once a newer tag appears
+    if let (Some(old), Some(new)) = (&url.tag, &rev.tag) {
+        let newer =
+            vercmp(new.as_str(), old.as_str()) == Ordering::Greater && rev.commit != url.commit;
+        debug!(
+            "devel check {}: '{}' < '{}' newer: {}",
+            url.url, old, new, newer
+        );
+        if newer {
+            return Ok(());
+        }
+        bail!(tr!("package does not have an update"))
+    }
+
     debug!(
         "devel check {}: '{}' == '{}' different: {}",
         url.url,
         url.commit,
-        sha,
-        url.commit != sha
+        rev.commit,
+        url.commit != rev.commit
     );
-    if sha != *url.commit {
+    if rev.commit != *url.commit {
         return Ok(());
     }
 
@@ -495,10 +1160,7 @@ pub async fn fetch_devel_info(
     bases: &[Base],
     srcinfos: &HashMap<String, Srcinfo>,
 ) -> Result<DevelInfo> {
-    let mut devel_info = DevelInfo::default();
-
-    let mut parsed = Vec::new();
-    let mut futures = Vec::new();
+    let mut pkgs = Vec::new();
 
     for base in bases {
         let srcinfo = match base {
@@ -506,40 +1168,61 @@ pub async fn fetch_devel_info(
             Base::Pkgbuild(c) => Some(c.srcinfo.as_ref()),
         };
 
-        let srcinfo = match srcinfo {
-            Some(v) => v,
-            None => continue,
-        };
+        if let Some(srcinfo) = srcinfo {
+            pkgs.push((base.package_base(), srcinfo));
+        }
+    }
+
+    fetch_srcinfo_devel_info(config, &pkgs).await
+}
+
+async fn fetch_srcinfo_devel_info(config: &Config, pkgs: &[(&str, &Srcinfo)]) -> Result<DevelInfo> {
+    let mut devel_info = DevelInfo::default();
+    let remotes = Remotes::new(config, false);
 
+    let mut parsed = Vec::new();
+    let mut futures = Vec::new();
+
+    for &(pkgbase, srcinfo) in pkgs {
         // TODO dont do this
         let arch = config.alpm.architectures().first().unwrap_or_default();
+        let tags = config.devel_tags.is_match(pkgbase);
         for url in srcinfo.base.source.arch(arch) {
-            if let Some((remote, _, branch)) = parse_url(url) {
+            if let Some(source) = parse_url(url) {
+                if source.tag.is_some() && !tags {
+                    continue;
+                }
                 let future = ls_remote(
-                    config.color.error,
-                    &config.git_bin,
-                    &config.git_flags,
-                    remote.clone(),
-                    branch,
+                    config,
+                    &remotes,
+                    source.vcs,
+                    source.remote.clone(),
+                    source.branch,
+                    source.tag.is_some(),
                 );
                 futures.push(future);
-                parsed.push((remote, base.package_base().to_string(), branch));
+                parsed.push((source, pkgbase.to_string()));
             }
         }
     }
 
     let commits = join_all(futures).await;
-    for ((remote, pkgbase, branch), commit) in parsed.into_iter().zip(commits) {
-        match commit {
+    remotes.save(config)?;
+    remotes.save_status(config)?;
+
+    for ((source, pkgbase), rev) in parsed.into_iter().zip(commits) {
+        match rev {
             Err(e) => print_error(
                 config.color.error,
                 e.context(tr!("failed to lookup: {}", pkgbase)),
             ),
-            Ok(commit) => {
+            Ok(rev) => {
                 let url_info = RepoInfo {
-                    url: remote,
-                    branch: branch.map(|s| s.to_string()),
-                    commit,
+                    url: source.remote,
+                    branch: source.branch.map(|s| s.to_string()),
+                    commit: rev.commit,
+                    vcs: source.vcs,
+                    tag: source.tag.map(|s| s.to_string()),
                 };
 
                 devel_info
@@ -0,0 +1,175 @@
+use crate::config::Config;
+use crate::install::Status;
+use crate::util::{ask, load_state, save_state};
+
+use std::collections::{BTreeMap, HashMap};
+use std::env::var_os;
+use std::fs::{read_dir, symlink_metadata};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use aur_depends::Base;
+use indicatif::HumanBytes;
+use nix::sys::statvfs::statvfs;
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+/// The space a base took up the last time it was built.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone)]
+struct Size {
+    // the clone dir after building, before src/ and pkg/ are cleaned
+    build: u64,
+    // the built packages
+    pkg: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+struct Sizes {
+    bases: BTreeMap<String, Size>,
+}
+
+impl Sizes {
+    fn load(config: &Config) -> Result<Self> {
+        let sizes = load_state(&config.state_dir, "buildsizes.toml")?;
+        Ok(sizes.unwrap_or_default())
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        save_state(&config.state_dir, "buildsizes.toml", self)
+    }
+}
+
+// symlinks are not followed and unreadable files are skipped
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| dir_size(&e.path()))
+        .sum::<u64>()
+        + metadata.len()
+}
+
+/// Records how much space a base took up after being built.
+pub fn record(
+    config: &Config,
+    pkgbase: &str,
+    dir: &Path,
+    pkgdests: &HashMap<String, String>,
+) -> Result<()> {
+    let size = Size {
+        build: dir_size(dir),
+        pkg: pkgdests.values().map(|p| dir_size(Path::new(p))).sum(),
+    };
+
+    let mut sizes = Sizes::load(config)?;
+    sizes.bases.insert(pkgbase.to_string(), size);
+    sizes.save(config)
+}
+
+// bases that were never built by paru are guessed from the size of the installed packages
+fn estimate(config: &Config, sizes: &Sizes, base: &Base) -> Size {
+    if let Some(size) = sizes.bases.get(base.package_base()) {
+        return *size;
+    }
+
+    let db = config.alpm.localdb();
+    let installed = base
+        .packages()
+        .filter_map(|p| db.pkg(p).ok())
+        .map(|p| p.isize().max(0) as u64)
+        .sum();
+    Size {
+        build: installed,
+        pkg: installed,
+    }
+}
+
+fn existing(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("/"))
+}
+
+/// Compares the space the bases are expected to need against the free space
+/// where they will be built and where the packages will end up.
+pub fn check(config: &Config, build: &[Base], repo: Option<(&str, &str)>) -> Result<()> {
+    let sizes = Sizes::load(config)?;
+    let pkgdest = repo
+        .map(|(_, file)| PathBuf::from(file))
+        .or_else(|| var_os("PKGDEST").map(PathBuf::from));
+    let mut needed: Vec<(PathBuf, u64)> = Vec::new();
+
+    for base in build {
+        let size = estimate(config, &sizes, base);
+        let dir = match base {
+            Base::Aur(_) => config.build_dir.clone(),
+            Base::Pkgbuild(b) => match config.pkgbuild_repos.repo(&b.repo) {
+                Some(repo) => repo.path.clone(),
+                None => continue,
+            },
+        };
+
+        if config.chroot {
+            needed.push((config.chroot_dir.clone(), size.build));
+        }
+        needed.push((pkgdest.clone().unwrap_or_else(|| dir.clone()), size.pkg));
+        needed.push((dir, size.build));
+    }
+
+    // paths on the same filesystem share its free space
+    let mut filesystems: Vec<(u64, PathBuf, u64)> = Vec::new();
+    for (path, size) in needed {
+        let path = existing(&path);
+        let dev = symlink_metadata(path)
+            .with_context(|| tr!("failed to read file: {}", path.display()))?
+            .dev();
+        match filesystems.iter_mut().find(|(d, _, _)| *d == dev) {
+            Some((_, _, needed)) => *needed += size,
+            None => filesystems.push((dev, path.to_path_buf(), size)),
+        }
+    }
+
+    let mut short = Vec::new();
+    for (_, path, needed) in filesystems {
+        let stat =
+            statvfs(&path).with_context(|| tr!("failed to read free space: {}", path.display()))?;
+        let free = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+        if needed > free {
+            short.push((path, needed, free));
+        }
+    }
+
+    if short.is_empty() {
+        return Ok(());
+    }
+
+    let c = config.color;
+    for (path, needed, free) in short {
+        eprintln!(
+            "{} {}",
+            c.warning.paint("::"),
+            tr!(
+                "{path} may not have enough space: {needed} needed, {free} free",
+                path = path.display(),
+                needed = HumanBytes(needed),
+                free = HumanBytes(free)
+            )
+        );
+    }
+
+    if !ask(config, &tr!("Continue building?"), true) {
+        return Status::err(1);
+    }
+
+    Ok(())
+}
@@ -5,15 +5,18 @@ use crate::config::Config;
 
 use std::ffi::OsStr;
 use std::fmt::{Debug, Display, Formatter};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
 use std::path::Path;
 use std::process::{Child, Command, Output, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use anyhow::{bail, Context, Result};
 use log::debug;
+use nix::unistd::Uid;
 use signal_hook::consts::signal::*;
 use signal_hook::flag as signal_flag;
 use std::sync::LazyLock;
@@ -78,16 +81,13 @@ fn command_err(cmd: &Command) -> String {
     )
 }
 
-pub fn command_status(cmd: &mut Command) -> Result<Status> {
-    debug!("running command: {:?}", cmd);
+// runs f with signals left to the children and exits if one was caught meanwhile
+fn forward_signals<T>(f: impl FnOnce() -> T) -> T {
     let term = &*CAUGHT_SIGNAL;
 
     DEFAULT_SIGNALS.store(false, Ordering::Relaxed);
 
-    let ret = cmd
-        .status()
-        .map(|s| Status(s.code().unwrap_or(1)))
-        .with_context(|| command_err(cmd));
+    let ret = f();
 
     DEFAULT_SIGNALS.store(true, Ordering::Relaxed);
 
@@ -97,6 +97,94 @@ pub fn command_status(cmd: &mut Command) -> Result<Status> {
     }
 }
 
+pub fn command_status(cmd: &mut Command) -> Result<Status> {
+    debug!("running command: {:?}", cmd);
+
+    forward_signals(|| {
+        cmd.status()
+            .map(|s| Status(s.code().unwrap_or(1)))
+            .with_context(|| command_err(cmd))
+    })
+}
+
+fn tee<R: Read, W: Write>(mut input: R, mut out: W, file: &Mutex<File>) {
+    let mut buf = [0; 8192];
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let _ = out.write_all(&buf[..n]);
+        let _ = out.flush();
+        let _ = file.lock().unwrap().write_all(&buf[..n]);
+    }
+}
+
+// like command_status but the output is also written to log
+pub fn command_status_log(cmd: &mut Command, log: &Path) -> Result<Status> {
+    debug!("running command: {:?} (log: {})", cmd, log.display());
+    let file =
+        File::create(log).with_context(|| tr!("failed to create log file: {}", log.display()))?;
+    let file = Mutex::new(file);
+
+    forward_signals(|| {
+        cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| command_err(cmd))
+            .and_then(|mut child| {
+                let stdout = child.stdout.take().unwrap();
+                let stderr = child.stderr.take().unwrap();
+                thread::scope(|s| {
+                    s.spawn(|| tee(stdout, std::io::stdout(), &file));
+                    s.spawn(|| tee(stderr, std::io::stderr(), &file));
+                });
+                child
+                    .wait()
+                    .map(|s| Status(s.code().unwrap_or(1)))
+                    .with_context(|| command_err(cmd))
+            })
+    })
+}
+
+// runs the commands with at most `jobs` running at once
+pub fn command_status_jobs(cmds: Vec<Command>, jobs: usize) -> Vec<Result<Status>> {
+    let next = AtomicUsize::new(0);
+    let cmds = cmds.into_iter().map(Mutex::new).collect::<Vec<_>>();
+    let results = Mutex::new(cmds.iter().map(|_| None).collect::<Vec<_>>());
+
+    forward_signals(|| {
+        thread::scope(|s| {
+            for _ in 0..jobs.clamp(1, cmds.len().max(1)) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let cmd = match cmds.get(i) {
+                        Some(cmd) => cmd,
+                        None => break,
+                    };
+                    let mut cmd = cmd.lock().unwrap();
+                    debug!("running command: {:?}", cmd);
+
+                    let ret = cmd
+                        .status()
+                        .map(|s| Status(s.code().unwrap_or(1)))
+                        .with_context(|| command_err(&cmd));
+                    results.lock().unwrap()[i] = Some(ret);
+                });
+            }
+        });
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
 pub fn command(cmd: &mut Command) -> Result<()> {
     command_status(cmd)?
         .success()
@@ -104,19 +192,17 @@ pub fn command(cmd: &mut Command) -> Result<()> {
     Ok(())
 }
 
+pub fn command_log(cmd: &mut Command, log: &Path) -> Result<()> {
+    command_status_log(cmd, log)?
+        .success()
+        .with_context(|| command_err(cmd))?;
+    Ok(())
+}
+
 pub fn command_output(cmd: &mut Command) -> Result<Output> {
     debug!("running command: {:?}", cmd);
-    let term = &*CAUGHT_SIGNAL;
 
-    DEFAULT_SIGNALS.store(false, Ordering::Relaxed);
-
-    let ret = cmd.output().with_context(|| command_err(cmd));
-
-    DEFAULT_SIGNALS.store(true, Ordering::Relaxed);
-    let ret = match term.swap(0, Ordering::Relaxed) {
-        0 => ret?,
-        n => std::process::exit(128 + n as i32),
-    };
+    let ret = forward_signals(|| cmd.output().with_context(|| command_err(cmd)))?;
 
     if !ret.status.success() {
         bail!(
@@ -181,8 +267,11 @@ fn wait_for_lock(config: &Config) {
 }
 
 fn new_pacman<S: AsRef<str> + Display + Debug>(config: &Config, args: &Args<S>) -> Command {
-    let mut cmd = if config.need_root {
+    if config.need_root {
         wait_for_lock(config);
+    }
+
+    let mut cmd = if config.need_root && !Uid::current().is_root() {
         let mut cmd = Command::new(&config.sudo_bin);
         cmd.args(&config.sudo_flags).arg(args.bin.as_ref());
         cmd
@@ -211,13 +300,25 @@ pub fn pacman_output<S: AsRef<str> + Display + std::fmt::Debug>(
     command_output(&mut cmd)
 }
 
-fn new_makepkg<S: AsRef<OsStr>>(
+/// Runs the program as BuildUser when paru is run as root.
+pub fn build_user_command<S: AsRef<OsStr>>(config: &Config, program: S) -> Command {
+    match config.build_user() {
+        Some(user) => {
+            let mut cmd = Command::new("runuser");
+            cmd.args(["-u", user, "--"]).arg(program);
+            cmd
+        }
+        None => Command::new(program),
+    }
+}
+
+pub fn new_makepkg<S: AsRef<OsStr>>(
     config: &Config,
     dir: &Path,
     args: &[S],
     pkgdest: Option<&str>,
 ) -> Command {
-    let mut cmd = Command::new(&config.makepkg_bin);
+    let mut cmd = build_user_command(config, &config.makepkg_bin);
     if let Some(mconf) = &config.makepkg_conf {
         cmd.arg("--config").arg(mconf);
     }
@@ -0,0 +1,176 @@
+use crate::config::{Config, Op};
+use crate::devel::{load_devel_info, save_devel_info, RepoInfo};
+use crate::install::{self, set_install_reason};
+use crate::util::{load_toml, pkg_base_or_name, save_toml};
+use crate::{printtr, repo};
+
+use std::path::Path;
+
+use alpm::PackageReason;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Aur,
+    Pkgbuild(String),
+    Repo(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Reason {
+    Explicit,
+    Depend,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportPkg {
+    pub name: String,
+    pub pkgbase: String,
+    pub source: Source,
+    pub reason: Reason,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devel: Vec<RepoInfo>,
+}
+
+/// The foreign packages installed on a system, used with --import to
+/// install the same packages on another machine.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Export {
+    #[serde(default, rename = "pkg")]
+    pub pkgs: Vec<ExportPkg>,
+}
+
+impl Export {
+    pub fn load(path: &Path) -> Result<Self> {
+        load_toml(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_toml(path, self)
+    }
+}
+
+impl ExportPkg {
+    // repos that don't exist on this machine leave the routing to
+    // split_repo_aur_targets like any other target
+    fn target(&self, config: &Config) -> String {
+        match &self.source {
+            Source::Repo(repo) if config.alpm.syncdbs().iter().any(|db| db.name() == repo) => {
+                format!("{}/{}", repo, self.name)
+            }
+            Source::Pkgbuild(repo) if config.pkgbuild_repos.repo(repo).is_some() => {
+                format!("{}/{}", repo, self.name)
+            }
+            Source::Aur => format!("{}/{}", config.aur_namespace(), self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+pub fn export(config: &Config, path: &Path) -> Result<i32> {
+    let devel_info = load_devel_info(config)?.unwrap_or_default();
+    let (repo_dbs, aur_dbs) = repo::repo_aur_dbs(config);
+    let mut export = Export::default();
+
+    for pkg in config.alpm.localdb().pkgs() {
+        let name = pkg.name();
+        if repo_dbs.iter().any(|db| db.pkg(name).is_ok()) {
+            continue;
+        }
+
+        let source = if let Some(db) = aur_dbs.iter().find(|db| db.pkg(name).is_ok()) {
+            Source::Repo(db.name().to_string())
+        } else if let Some((repo_pkg, _)) = config.pkgbuild_repos.pkg(config, name) {
+            Source::Pkgbuild(repo_pkg.repo.clone())
+        } else {
+            Source::Aur
+        };
+
+        let reason = match pkg.reason() {
+            PackageReason::Explicit => Reason::Explicit,
+            PackageReason::Depend => Reason::Depend,
+        };
+
+        let pkgbase = pkg_base_or_name(pkg);
+        let mut devel = devel_info
+            .info
+            .get(pkgbase)
+            .into_iter()
+            .flat_map(|info| &info.repos)
+            .cloned()
+            .collect::<Vec<_>>();
+        devel.sort_by(|a, b| a.url.cmp(&b.url));
+
+        export.pkgs.push(ExportPkg {
+            name: name.to_string(),
+            pkgbase: pkgbase.to_string(),
+            source,
+            reason,
+            devel,
+        });
+    }
+
+    export.pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+    export.save(path)?;
+
+    printtr!(
+        "exported {} packages to {}",
+        export.pkgs.len(),
+        path.display()
+    );
+    Ok(0)
+}
+
+pub async fn import(config: &mut Config, path: &Path) -> Result<i32> {
+    let export = Export::load(path)?;
+
+    let db = config.alpm.localdb();
+    let missing = export
+        .pkgs
+        .iter()
+        .filter(|p| db.pkg(p.name.as_str()).is_err())
+        .collect::<Vec<_>>();
+    let targets = missing.iter().map(|p| p.target(config)).collect::<Vec<_>>();
+
+    if targets.is_empty() {
+        printtr!(" there is nothing to do");
+        return Ok(0);
+    }
+
+    config.set_op_args_globals(Op::Sync);
+    install::install(config, &targets).await?;
+
+    // everything was installed as a target so restore the dependencies
+    let depends = missing
+        .iter()
+        .filter(|p| p.reason == Reason::Depend)
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    set_install_reason(config, "asdeps", &depends)?;
+
+    // the devel info is needed for the imported packages to get devel updates
+    let mut devel_info = load_devel_info(config)?.unwrap_or_default();
+    let alpm = config.new_alpm()?;
+    let db = alpm.localdb();
+    for pkg in &missing {
+        if pkg.devel.is_empty() || db.pkg(pkg.name.as_str()).is_err() {
+            continue;
+        }
+        // the install just recorded the latest commits, the exported ones win
+        let repos = &mut devel_info
+            .info
+            .entry(pkg.pkgbase.clone())
+            .or_default()
+            .repos;
+        for repo in &pkg.devel {
+            repos.replace(repo.clone());
+        }
+    }
+    save_devel_info(config, &devel_info)?;
+
+    Ok(0)
+}
@@ -27,6 +27,18 @@ pub fn help() {
     printtr!("Options without operation:");
     printtr!("    -c --clean            Remove unneeded dependencies");
     printtr!("       --gendb            Generates development package DB used for updating");
+    printtr!("       --fromclones       Use --gendb with the commits checked out in the clone dir");
+    printtr!("       --develstatus      Show the status of tracked development packages");
+    printtr!("       --resume [abort]   Continue or discard an interrupted AUR transaction");
+    printtr!("       --rollback [n]     Undo the last n transactions");
+    printtr!("       --writelock <file> Write a lockfile of installed AUR and PKGBUILD packages");
+    printtr!("       --apply <file>     Install and remove packages to match a manifest");
+    printtr!("       --export <file>    Write the installed foreign packages to a file");
+    printtr!("       --import <file>    Install the foreign packages from an exported file");
+    printtr!("       --retry-failed     Build the packages that failed last time again");
+    printtr!("       --log <package>    Print the latest build log of a package");
+    printtr!("       --providers        List the saved provider choices");
+    printtr!("       --resetproviders   Forget the saved provider choices");
     println!();
     printtr!("New options:");
     printtr!("       --repo              Assume targets are from the repositories");
@@ -50,6 +62,8 @@ pub fn help() {
     printtr!("    --pkgctl    <file>     pkgctl command to use");
     printtr!("    --bat       <file>     bat command to use");
     printtr!("    --batflags  <flags>    Pass arguments to bat");
+    printtr!("    --linter    <file>     Linter command to use for --lint");
+    printtr!("    --linterflags <flags>  Pass arguments to the linter");
     printtr!("    --gpg       <file>     gpg command to use");
     printtr!("    --gpgflags  <flags>    Pass arguments to gpg");
     printtr!("    --fm        <file>     File manager to use for PKGBUILD review");
@@ -81,7 +95,17 @@ pub fn help() {
     printtr!("    --[no]sudoloop         Loop sudo calls in the background to avoid timeout");
     printtr!("    --[no]chroot           Build packages in a chroot");
     printtr!("    --[no]failfast         Exit as soon as building an AUR package fails");
+    printtr!("    --buildjobs <n>        Build up to n independent packages at once");
+    printtr!("    --buildmemorymax <n>   Limit the memory builds can use");
+    printtr!("    --buildcpuquota <n%>   Limit the CPU time builds can use");
+    printtr!("    --buildnice <n>        Run builds with the given niceness");
+    printtr!("    --builduser <user>     The user to build as when run as root");
+    printtr!("    --print-plan [json]    Print the resolved install plan and exit");
+    printtr!("    --locked    <file>     Build the commits recorded in a lockfile");
+    printtr!("    --[no]lint [policy]    Lint built packages (warn, ask or fail)");
+    printtr!("    --[no]verify-repro     Build packages twice and compare the results");
     printtr!("    --[no]keepsrc          Keep src/ and pkg/ dirs after building packages");
+    printtr!("    --[no]buildlog         Write the output of builds to a log file");
     printtr!("    --[no]sign             Sign packages with gpg");
     printtr!("    --[no]signdb           Sign databases with gpg");
     printtr!("    --[no]localrepo        Build packages into a local repo");
@@ -91,6 +115,13 @@ pub fn help() {
     );
     printtr!("    --develsuffixes        Suffixes used to decide if a package is a devel package");
     printtr!("    --ignoredevel          Ignore devel upgrades for specified packages");
+    printtr!("    --[no]develtags        Follow release tags for devel packages pinned to a tag");
+    printtr!("    --develtagfilter       Regex tags must match to be considered by --develtags");
+    printtr!("    --develjobs <n>        Max devel sources to check at once (0 for no limit)");
+    printtr!("    --develhostjobs <n>    Max devel sources to check at once per host");
+    printtr!("    --develcachetime <s>   Seconds to cache devel check results for");
+    printtr!("    --upstreamfile <file>  File containing upstream release rules");
+    printtr!("    --statedir <dir>       Directory used for paru's state files");
     printtr!("    --bottomup             Shows AUR's packages first and then repository's");
     printtr!("    --topdown              Shows repository's packages first and then AUR's");
     println!();
@@ -0,0 +1,280 @@
+use crate::config::Config;
+use crate::fmt::{date, print_indent};
+use crate::util::{ask, load_state, pkg_base_or_name, save_state, with_fresh_localdb};
+use crate::{exec, printtr, repo};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::path::PathBuf;
+
+use alpm_utils::DbListExt;
+use ansiterm::Style;
+use anyhow::{bail, ensure, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+// how many transactions are kept in the history file
+const MAX_TRANSACTIONS: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Change {
+    name: String,
+    base: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    old: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    new: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Transaction {
+    time: i64,
+    pkgs: Vec<Change>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct History {
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone)]
+struct Installed {
+    base: String,
+    version: String,
+}
+
+/// The versions of the local packages that don't come from a repo at the start
+/// of a transaction. Repo packages are left to pacman so they are never rolled
+/// back.
+#[derive(Debug)]
+pub struct Snapshot {
+    pkgs: BTreeMap<String, Installed>,
+}
+
+impl Snapshot {
+    pub fn new(config: &Config) -> Result<Self> {
+        let (repos, _) = repo::repo_aur_dbs(config);
+        let pkgs = with_fresh_localdb(config, |db| {
+            db.pkgs()
+                .iter()
+                .filter(|p| repos.pkg(p.name()).is_err())
+                .map(|p| {
+                    let installed = Installed {
+                        base: pkg_base_or_name(p).to_string(),
+                        version: p.version().to_string(),
+                    };
+                    (p.name().to_string(), installed)
+                })
+                .collect()
+        })?;
+        Ok(Snapshot { pkgs })
+    }
+
+    /// Compares against the current local db and saves what changed.
+    pub fn record(&self, config: &Config) -> Result<()> {
+        let after = Snapshot::new(config)?;
+        let mut pkgs = Vec::new();
+
+        for (name, old) in &self.pkgs {
+            match after.pkgs.get(name) {
+                Some(new) if new.version == old.version => (),
+                new => pkgs.push(Change {
+                    name: name.clone(),
+                    base: old.base.clone(),
+                    old: Some(old.version.clone()),
+                    new: new.map(|n| n.version.clone()),
+                }),
+            }
+        }
+
+        for (name, new) in &after.pkgs {
+            if !self.pkgs.contains_key(name) {
+                pkgs.push(Change {
+                    name: name.clone(),
+                    base: new.base.clone(),
+                    old: None,
+                    new: Some(new.version.clone()),
+                });
+            }
+        }
+
+        if pkgs.is_empty() {
+            return Ok(());
+        }
+
+        let mut history = load(config)?;
+        history.transactions.push(Transaction {
+            time: Utc::now().timestamp(),
+            pkgs,
+        });
+        let len = history.transactions.len();
+        history
+            .transactions
+            .drain(..len.saturating_sub(MAX_TRANSACTIONS));
+        save_state(&config.state_dir, "history.toml", &history)
+    }
+}
+
+fn load(config: &Config) -> Result<History> {
+    let history = load_state(&config.state_dir, "history.toml")?;
+    Ok(history.unwrap_or_default())
+}
+
+fn is_pkg_file(file: &str, name: &str, version: &str) -> bool {
+    let Some(rest) = file.strip_prefix(&format!("{}-{}-", name, version)) else {
+        return false;
+    };
+    match rest.split_once(".pkg.tar") {
+        Some((arch, ext)) => !arch.contains('-') && !ext.ends_with(".sig"),
+        None => false,
+    }
+}
+
+fn find_pkg_file(dirs: &[PathBuf], name: &str, version: &str) -> Option<PathBuf> {
+    for dir in dirs {
+        let Ok(entries) = read_dir(dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            if let Some(file) = entry.file_name().to_str() {
+                if is_pkg_file(file, name, version) {
+                    return Some(entry.path());
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// pacman's cache, then the local repos, then whatever was left in the clone dir
+fn search_dirs(config: &Config, base: &str) -> Vec<PathBuf> {
+    let mut dirs = config
+        .pacman
+        .cache_dir
+        .iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+
+    let (_, repos) = repo::repo_aur_dbs(config);
+    dirs.extend(
+        repos
+            .iter()
+            .filter_map(|r| repo::file(r))
+            .map(PathBuf::from),
+    );
+    dirs.push(config.build_dir.join(base));
+    dirs
+}
+
+pub fn rollback(config: &Config) -> Result<i32> {
+    let n = match config.targets.as_slice() {
+        [] => config.rollback.unwrap_or(1),
+        [n] => n
+            .parse()
+            .with_context(|| tr!("invalid number of transactions: {}", n))?,
+        _ => bail!(tr!("too many arguments")),
+    };
+    ensure!(n > 0, tr!("invalid number of transactions: {}", n));
+
+    let history = load(config)?;
+    let len = history.transactions.len();
+    if len < n {
+        bail!(tr!(
+            "can not roll back {} transactions, only {} recorded",
+            n,
+            len
+        ));
+    }
+
+    // the version before the oldest of the transactions being undone
+    let mut want = BTreeMap::new();
+    for transaction in &history.transactions[len - n..] {
+        for change in &transaction.pkgs {
+            if let Entry::Vacant(vacant) = want.entry(change.name.as_str()) {
+                vacant.insert(change);
+            }
+        }
+    }
+
+    let c = config.color;
+    let db = config.alpm.localdb();
+    let mut install = Vec::new();
+    let mut remove = Vec::new();
+    let mut missing = Vec::new();
+
+    for (name, change) in want {
+        let current = db.pkg(name).ok().map(|p| p.version().to_string());
+        if current == change.old {
+            continue;
+        }
+
+        match &change.old {
+            None => remove.push(name),
+            Some(old) => {
+                let dirs = search_dirs(config, &change.base);
+                match find_pkg_file(&dirs, name, old) {
+                    Some(file) => install.push(file),
+                    None => missing.push(format!("{}-{}", name, old)),
+                }
+            }
+        }
+    }
+
+    println!(
+        "{} {}",
+        c.action.paint("::"),
+        c.bold.paint(tr!(
+            "Rolling back to before {}...",
+            date(history.transactions[len - n].time)
+        ))
+    );
+
+    if !missing.is_empty() {
+        println!(
+            "{} {}",
+            c.warning.paint("::"),
+            c.bold.paint(tr!("No package files left to restore:"))
+        );
+        print!("    ");
+        print_indent(Style::new(), 4, 4, config.cols, "  ", &missing);
+    }
+
+    if install.is_empty() && remove.is_empty() {
+        printtr!(" there is nothing to do");
+        return Ok(!missing.is_empty() as i32);
+    }
+
+    if !missing.is_empty() && !ask(config, &tr!("Restore the remaining packages?"), false) {
+        return Ok(1);
+    }
+
+    let snapshot = Snapshot::new(config)?;
+    let ret = rollback_pkgs(config, &install, &remove);
+    snapshot.record(config)?;
+    ret?;
+
+    Ok(!missing.is_empty() as i32)
+}
+
+fn rollback_pkgs(config: &Config, install: &[PathBuf], remove: &[&str]) -> Result<()> {
+    if !install.is_empty() {
+        let mut args = config.pacman_globals();
+        args.op("upgrade");
+        args.targets = install.iter().filter_map(|p| p.to_str()).collect();
+        exec::pacman(config, &args)?.success()?;
+    }
+
+    if !remove.is_empty() {
+        let mut args = config.pacman_globals();
+        args.op("remove").arg("recursive");
+        args.targets = remove.to_vec();
+        exec::pacman(config, &args)?.success()?;
+    }
+
+    Ok(())
+}
@@ -3,36 +3,52 @@ use std::collections::{HashMap, HashSet};
 use std::env::var;
 use std::ffi::OsStr;
 use std::fmt::Write as _;
-use std::fs::{read_dir, read_link, OpenOptions};
-use std::io::{BufRead, Read, Write};
+use std::fs::{create_dir_all, read_dir, read_link, remove_dir_all, File, OpenOptions};
+use std::io::{BufRead, ErrorKind, Read, Write};
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::atomic::Ordering;
 
 use crate::args::{Arg, Args};
+use crate::buildlog;
 use crate::chroot::Chroot;
 use crate::clean::clean_untracked;
 use crate::completion::update_aur_cache;
-use crate::config::{Config, LocalRepos, Mode, Op, Sign, YesNoAllTree, YesNoAsk};
+use crate::config::{
+    Config, LintPolicy, LocalRepos, Mode, Op, ReproPolicy, Sign, YesNoAllTree, YesNoAsk,
+};
 use crate::devel::{fetch_devel_info, load_devel_info, save_devel_info, DevelInfo};
+use crate::diskspace;
 use crate::download::{self, Bases};
 use crate::exec::{command_status, has_command};
 use crate::fmt::{print_indent, print_install, print_install_verbose};
+use crate::history::Snapshot;
+use crate::journal::{Journal, JournalBase};
 use crate::keys::check_pgp_keys;
+use crate::limit::limit_build;
+use crate::lock::{self, Lockfile};
 use crate::pkgbuild::PkgbuildRepo;
+use crate::provider::Providers;
 use crate::resolver::{flags, resolver};
+use crate::retry::Failed;
 use crate::upgrade::{get_upgrades, Upgrades};
-use crate::util::{ask, repo_aur_pkgs, split_repo_aur_targets};
-use crate::{args, exec, news, print_error, printtr, repo};
+use crate::util::{ask, redirect_to_stderr, reopen_stdout, repo_aur_pkgs, split_repo_aur_targets};
+use crate::{args, exec, news, optdeps, plan, print_error, printtr, repo, repro, split};
 
 use alpm::{Alpm, Depend, Version};
 use alpm_utils::depends::{satisfies, satisfies_nover, satisfies_provide, satisfies_provide_nover};
 use alpm_utils::{DbListExt, Targ};
 use ansiterm::Style;
-use anyhow::{bail, ensure, Context, Result};
-use aur_depends::{Actions, Base, Conflict, DepMissing, RepoPackage};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use aur_depends::{
+    Actions, AurBase, AurPackage, Base, Conflict, DepMissing, PkgbuildPackage, PkgbuildPackages,
+    RepoPackage,
+};
 use log::debug;
-use raur::Cache;
+use nix::unistd::User;
+use raur::{Cache, Raur};
 use srcinfo::{ArchVecs, Srcinfo};
 use tr::tr;
 
@@ -53,6 +69,21 @@ impl Status {
     }
 }
 
+struct PreparedBuild {
+    dir: PathBuf,
+    pkgdests: HashMap<String, String>,
+    version: String,
+    needs_build: bool,
+}
+
+struct InstalledBase {
+    base: String,
+    version: String,
+    dir: PathBuf,
+    target: bool,
+    files: Vec<String>,
+}
+
 struct Installer {
     refresh: usize,
     sysupgrade: usize,
@@ -72,12 +103,60 @@ struct Installer {
     devel_info: DevelInfo,
     new_devel_info: DevelInfo,
     built: Vec<String>,
+    logs: HashMap<String, PathBuf>,
+    lock: Option<Lockfile>,
+    lints: Vec<(String, usize)>,
+    repro: Vec<(String, bool)>,
+    journal: Option<Journal>,
+    plan_stdout: Option<OwnedFd>,
+    installing: Vec<InstalledBase>,
+    // built targets waiting in the install queue
+    queued_targets: Vec<String>,
+    installed_targets: Vec<String>,
 }
 
 pub async fn install(config: &mut Config, targets_str: &[String]) -> Result<()> {
     let mut installer = Installer::new(config);
     installer.install_targets = !config.no_install;
-    installer.install(config, targets_str).await
+
+    if let Some(path) = &config.locked {
+        let lock = Lockfile::load(path)?;
+        lock.checkout_repos(config)?;
+        let targets = if targets_str.is_empty() {
+            lock.targets(config)
+        } else {
+            targets_str.to_vec()
+        };
+        installer.lock = Some(lock);
+        installer.install(config, &targets).await?;
+    } else {
+        installer.install(config, targets_str).await?;
+    }
+
+    // only plain -S installs offer optional dependencies, the others install
+    // a fixed set of packages
+    if config.print_plan.is_some()
+        || !installer.install_targets
+        || config.locked.is_some()
+        || config.apply.is_some()
+        || config.import.is_some()
+        || config.retry_failed
+    {
+        return Ok(());
+    }
+
+    let optdeps = optdeps::pick(config, &installer.installed_targets)?;
+    if optdeps.is_empty() {
+        return Ok(());
+    }
+
+    // a follow up transaction that only installs the picked packages, none of
+    // the flags of the first one apply to it
+    config.args = config.globals.clone();
+    config.args.arg("asdeps".to_string());
+    config.set_op_args_globals(Op::Sync);
+    let mut installer = Installer::new(config);
+    installer.install(config, &optdeps).await
 }
 
 pub async fn build_dirs(config: &mut Config, dirs: Vec<PathBuf>) -> Result<()> {
@@ -96,6 +175,167 @@ pub async fn build_dirs(config: &mut Config, dirs: Vec<PathBuf>) -> Result<()> {
     installer.install(config, &targets).await
 }
 
+pub async fn resume(config: &mut Config) -> Result<()> {
+    let journal = Journal::load(config)?.context(tr!("there is no transaction to resume"))?;
+    journal.check_local(config)?;
+
+    if nix::unistd::getuid().is_root() && config.build_user().is_none() {
+        bail!(tr!("can't install AUR package as root"));
+    }
+    chown_build_user(config, &config.build_dir)?;
+
+    let mut installer = Installer::new(config);
+    installer.install_targets = journal.install_targets;
+    installer.remove_make = journal.remove_make.clone();
+    installer.sudo_loop(config)?;
+    config.set_op_args_globals(Op::Sync);
+
+    let mut build = journal_bases(config, &journal).await?;
+    let bases = build
+        .iter()
+        .filter_map(|b| match b {
+            Base::Aur(base) => Some(base.clone()),
+            Base::Pkgbuild(_) => None,
+        })
+        .collect();
+    installer.read_srcinfos(config, &Bases { bases })?;
+    installer.journal = Some(journal);
+
+    let c = config.color;
+    if !build.is_empty() {
+        println!(
+            "{} {}",
+            c.action.paint("::"),
+            c.bold.paint(tr!("Resuming transaction..."))
+        );
+        print!("    ");
+        print_indent(
+            Style::new(),
+            0,
+            4,
+            config.cols,
+            "  ",
+            build.iter().map(|b| b.package_base()),
+        );
+        if !ask(config, &tr!("Proceed with installation?"), true) {
+            return Status::err(1);
+        }
+    }
+
+    let snapshot = Snapshot::new(config)?;
+    let mut err = Ok(());
+    if !build.is_empty() {
+        err = installer.build_install_pkgbuilds(config, &mut build).await;
+    }
+
+    let err = installer.finish_transaction(config, &build, &[], err);
+    if let Err(e) = snapshot.record(config) {
+        print_error(config.color.error, e);
+    }
+    err
+}
+
+// the built packages and resolved dependencies only hold for the saved version
+fn check_journal_version(base: &JournalBase, version: &str) -> Result<()> {
+    ensure!(
+        base.version == version,
+        tr!(
+            "{pkgbase} has changed since the transaction was saved ({old} => {new})",
+            pkgbase = base.pkgbase,
+            old = base.version,
+            new = version
+        )
+    );
+    Ok(())
+}
+
+// recreates the bases left to install from the journal without resolving again
+async fn journal_bases(config: &mut Config, journal: &Journal) -> Result<Vec<Base>> {
+    let names = journal
+        .bases
+        .iter()
+        .filter(|b| b.repo.is_none() && !b.installed)
+        .flat_map(|b| &b.pkgs)
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    let aur = config.raur.cache_info(&mut config.cache, &names).await?;
+
+    let mut build = Vec::new();
+
+    for base in journal.bases.iter().filter(|b| !b.installed) {
+        let build_base = base.build && !base.built;
+
+        match &base.repo {
+            None => {
+                let pkgs = base
+                    .pkgs
+                    .iter()
+                    .map(|p| {
+                        let pkg = aur
+                            .iter()
+                            .find(|a| a.name == p.name)
+                            .with_context(|| tr!("could not find '{}' in the AUR", p.name))?;
+                        check_journal_version(base, &pkg.version)?;
+                        Ok(AurPackage {
+                            pkg: pkg.clone(),
+                            make: p.make,
+                            target: p.target,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                build.push(Base::Aur(AurBase {
+                    pkgs,
+                    build: build_base,
+                }));
+            }
+            Some(repo) => {
+                let srcinfo = &config
+                    .pkgbuild_repos
+                    .repo(repo)
+                    .and_then(|r| r.base(config, &base.pkgbase))
+                    .with_context(|| {
+                        tr!(
+                            "could not find '{}' in pkgbuild repo '{}'",
+                            base.pkgbase,
+                            repo
+                        )
+                    })?
+                    .srcinfo;
+                check_journal_version(base, &srcinfo.version())?;
+
+                let pkgs = base
+                    .pkgs
+                    .iter()
+                    .map(|p| {
+                        let pkg = srcinfo
+                            .pkgs
+                            .iter()
+                            .find(|s| s.pkgname == p.name)
+                            .with_context(|| {
+                                tr!("could not find package '{}' in '{}'", p.name, base.pkgbase)
+                            })?;
+                        Ok(PkgbuildPackage {
+                            pkg: pkg.clone(),
+                            make: p.make,
+                            target: p.target,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                build.push(Base::Pkgbuild(PkgbuildPackages {
+                    repo: repo.clone(),
+                    srcinfo: Box::new(srcinfo.clone()),
+                    pkgs,
+                    build: build_base,
+                }));
+            }
+        }
+    }
+
+    Ok(build)
+}
+
 impl Installer {
     fn new(config: &Config) -> Self {
         let mut fetch = config.fetch.clone();
@@ -121,6 +361,15 @@ impl Installer {
             devel_info: DevelInfo::default(),
             new_devel_info: DevelInfo::default(),
             built: Vec::new(),
+            logs: HashMap::new(),
+            lock: None,
+            lints: Vec::new(),
+            repro: Vec::new(),
+            journal: None,
+            plan_stdout: None,
+            installing: Vec::new(),
+            queued_targets: Vec::new(),
+            installed_targets: Vec::new(),
         }
     }
 
@@ -141,6 +390,11 @@ impl Installer {
         args.targets(targets.iter().map(|i| i.as_str()));
         exec::pacman(config, &args)?.success()?;
         config.args.remove("y").remove("refresh");
+        self.installed_targets.extend(
+            targets
+                .iter()
+                .map(|t| t.rsplit('/').next().unwrap_or(t).to_string()),
+        );
         config.args.remove("u").remove("sysupgrade");
         Ok(())
     }
@@ -191,6 +445,23 @@ impl Installer {
 
         download::new_aur_pkgbuilds(config, bases, &self.srcinfos).await?;
 
+        if let Some(lock) = &self.lock {
+            for base in &bases.bases {
+                let pkgbase = base.package_base();
+                let commit = lock
+                    .base(pkgbase, None)?
+                    .commit
+                    .as_deref()
+                    .with_context(|| tr!("no commit for '{}' in the lockfile", pkgbase))?;
+                lock::checkout(config, &config.build_dir.join(pkgbase), commit, pkgbase)?;
+                self.srcinfos.remove(pkgbase);
+            }
+        }
+
+        self.read_srcinfos(config, bases)
+    }
+
+    fn read_srcinfos(&mut self, config: &Config, bases: &Bases) -> Result<()> {
         for base in &bases.bases {
             if self.srcinfos.contains_key(base.package_base()) {
                 continue;
@@ -392,7 +663,10 @@ impl Installer {
 
             debug!("flushing install queue");
             args.targets = self.install_queue.iter().map(|s| s.as_str()).collect();
-            exec::pacman(config, &args)?.success()?;
+            let ret = exec::pacman(config, &args).and_then(|s| s.success().map_err(Into::into));
+            let hook = self.post_install_command(config, ret.is_ok());
+            ret?;
+            hook?;
 
             if config.devel {
                 save_devel_info(config, &self.devel_info)?;
@@ -403,6 +677,11 @@ impl Installer {
             self.deps.clear();
             self.exp.clear();
             self.install_queue.clear();
+            self.installed_targets.append(&mut self.queued_targets);
+        }
+
+        if let Some(journal) = &mut self.journal {
+            journal.set_installed(config)?;
         }
         Ok(())
     }
@@ -442,7 +721,52 @@ impl Installer {
             }
         }
 
+        if !self.lints.is_empty() {
+            let c = config.color;
+            println!(
+                "{} {}",
+                c.action.paint("::"),
+                c.bold.paint(tr!("Lint summary:"))
+            );
+            for (base, findings) in &self.lints {
+                println!("    {}: {}", base, tr!("{} findings", findings));
+            }
+        }
+
+        if !self.repro.is_empty() {
+            let c = config.color;
+            println!(
+                "{} {}",
+                c.action.paint("::"),
+                c.bold.paint(tr!("Reproducibility summary:"))
+            );
+            for (base, reproducible) in &self.repro {
+                if *reproducible {
+                    println!("    {}: {}", base, tr!("reproducible"));
+                } else {
+                    println!("    {}: {}", base, c.error.paint(tr!("not reproducible")));
+                }
+            }
+        }
+
         if !self.failed.is_empty() {
+            let logs = self
+                .failed
+                .iter()
+                .filter_map(|f| Some((f, self.logs.get(f.package_base())?)))
+                .collect::<Vec<_>>();
+            if !logs.is_empty() {
+                let c = config.color;
+                println!(
+                    "{} {}",
+                    c.error.paint("::"),
+                    c.bold.paint(tr!("Build logs:"))
+                );
+                for (base, log) in logs {
+                    println!("    {}: {}", base, log.display());
+                }
+            }
+
             let failed = self
                 .failed
                 .iter()
@@ -528,17 +852,15 @@ impl Installer {
     }
 
     // TODO: sort out args
-    fn build_pkgbuild(
+    fn download_pkgbuild(
         &mut self,
         config: &mut Config,
         base: &mut Base,
         repo: Option<(&str, &str)>,
         dir: &Path,
-    ) -> Result<(HashMap<String, String>, String)> {
+    ) -> Result<PreparedBuild> {
         let c = config.color;
         let pkgdest = repo.map(|r| r.1);
-        let mut env = config.env.clone();
-        env.extend(pkgdest.map(|p| ("PKGDEST".to_string(), p.to_string())));
 
         if config.chroot {
             let mut extra = Vec::new();
@@ -549,9 +871,34 @@ impl Installer {
                 config.chroot_flags.iter().map(|s| s.as_str()).collect();
             chroot_flags.push("-cu");
             self.chroot
-                .build(dir, &extra, &chroot_flags, &["-ofA"], &config.env)
+                .build(
+                    config,
+                    dir,
+                    &extra,
+                    &chroot_flags,
+                    &["-ofA"],
+                    &config.env,
+                    None,
+                )
                 .with_context(|| tr!("failed to download sources for '{}'", base))?;
 
+            if let Some(lock) = &self.lock {
+                if !lock
+                    .base(base.package_base(), base_repo(base))?
+                    .sources
+                    .is_empty()
+                {
+                    eprintln!(
+                        "{} {}",
+                        c.warning.paint("::"),
+                        tr!(
+                            "{}: source commits can not be locked when building in a chroot",
+                            base
+                        )
+                    );
+                }
+            }
+
             if !self.chroot.extra_pkgs.is_empty() {
                 let mut pkgs = vec!["pacman", "-S", "--asdeps", "--needed", "--noconfirm", "--"];
                 pkgs.extend(self.chroot.extra_pkgs.iter().map(|s| s.as_str()));
@@ -567,14 +914,35 @@ impl Installer {
                 .success()
                 .with_context(|| tr!("failed to download sources for '{}'", base))?;
 
+            let locked = match &self.lock {
+                Some(lock) => Some(lock.base(base.package_base(), base_repo(base))?),
+                None => None,
+            };
+            let lock_sources = locked.is_some_and(|l| !l.sources.is_empty());
+
             // pkgver bump
             let mut args = vec!["-ofA"];
+            if lock_sources {
+                // prepare() has to see the locked sources so it runs when extracting again
+                args.push("--noprepare");
+            }
             if !config.keep_src {
                 args.push("-C");
             }
             exec::makepkg(config, dir, &args)?
                 .success()
                 .with_context(|| tr!("failed to build '{}'", base))?;
+
+            if let Some(locked) = locked.filter(|_| lock_sources) {
+                let srcinfo = match &*base {
+                    Base::Aur(_) => self.srcinfos.get(base.package_base()).unwrap(),
+                    Base::Pkgbuild(c) => &*c.srcinfo,
+                };
+                lock::lock_sources(config, locked, srcinfo, dir)?;
+                exec::makepkg(config, dir, &["-ofA"])?
+                    .success()
+                    .with_context(|| tr!("failed to build '{}'", base))?;
+            }
         }
 
         printtr!("{}: parsing pkg list...", base);
@@ -585,32 +953,7 @@ impl Installer {
         }
 
         let needs_build = needs_build(config, base, &pkgdests, &version);
-        if needs_build {
-            // actual build
-            if config.chroot {
-                let mut extra = Vec::new();
-                if config.repos == LocalRepos::None {
-                    extra.extend(self.built.iter().map(|s| s.as_str()));
-                }
-                self.chroot
-                    .build(
-                        dir,
-                        &extra,
-                        &config.chroot_flags,
-                        &["-feA", "--noconfirm", "--noprepare", "--holdver"],
-                        &env,
-                    )
-                    .with_context(|| tr!("failed to build '{}'", base))?;
-            } else {
-                let mut args = vec!["-feA", "--noconfirm", "--noprepare", "--holdver"];
-                if !config.keep_src {
-                    args.push("-c");
-                }
-                exec::makepkg_dest(config, dir, &args, pkgdest)?
-                    .success()
-                    .with_context(|| tr!("failed to build '{}'", base))?;
-            }
-        } else {
+        if !needs_build {
             println!(
                 "{} {}",
                 c.warning.paint("::"),
@@ -622,11 +965,138 @@ impl Installer {
             )
         }
 
-        let debug_paths = self.debug_paths(config, base, &pkgdests)?;
+        Ok(PreparedBuild {
+            dir: dir.to_path_buf(),
+            pkgdests,
+            version,
+            needs_build,
+        })
+    }
+
+    // the work dirs are cleaned by finish_pkgbuild once their size is recorded
+    fn makepkg_args() -> Vec<&'static str> {
+        vec!["-feA", "--noconfirm", "--noprepare", "--holdver"]
+    }
+
+    fn makepkg_pkgbuild(
+        &self,
+        config: &Config,
+        base: &Base,
+        repo: Option<(&str, &str)>,
+        dir: &Path,
+        log: Option<&Path>,
+    ) -> Result<()> {
+        let pkgdest = repo.map(|r| r.1);
+        let mut env = config.env.clone();
+        env.extend(pkgdest.map(|p| ("PKGDEST".to_string(), p.to_string())));
+
+        if config.chroot {
+            let mut extra = Vec::new();
+            if config.repos == LocalRepos::None {
+                extra.extend(self.built.iter().map(|s| s.as_str()));
+            }
+            env.extend(source_date_epoch(config, dir)?);
+            self.chroot
+                .build(
+                    config,
+                    dir,
+                    &extra,
+                    &config.chroot_flags,
+                    &Self::makepkg_args(),
+                    &env,
+                    log,
+                )
+                .with_context(|| tr!("failed to build '{}'", base))?;
+        } else {
+            let args = Self::makepkg_args();
+            let cmd = exec::new_makepkg(config, dir, &args, pkgdest);
+            let mut cmd = limit_build(config, cmd)?;
+            // teeing takes the terminal away from makepkg so only do it when asked to
+            let status = match log {
+                Some(log) => exec::command_status_log(&mut cmd, log)?,
+                None => exec::command_status(&mut cmd)?,
+            };
+            status
+                .success()
+                .with_context(|| tr!("failed to build '{}'", base))?;
+        }
+
+        Ok(())
+    }
+
+    // builds the base again in a clean chroot and compares the packages to the first build
+    fn verify_repro(
+        &mut self,
+        config: &Config,
+        base: &Base,
+        prepared: &PreparedBuild,
+    ) -> Result<()> {
+        let Some(policy) = config.verify_repro else {
+            return Ok(());
+        };
+
+        printtr!("{}: verifying reproducibility...", base);
+        let dest = tempfile::Builder::new().prefix("paru-repro").tempdir()?;
+        let mut env = config.env.clone();
+        env.push((
+            "PKGDEST".to_string(),
+            dest.path().to_string_lossy().into_owned(),
+        ));
+        env.extend(source_date_epoch(config, &prepared.dir)?);
+
+        let mut extra = Vec::new();
+        if config.repos == LocalRepos::None {
+            extra.extend(self.built.iter().map(|s| s.as_str()));
+        }
+        let mut chroot_flags = config.chroot_flags.clone();
+        chroot_flags.push("-c".to_string());
+        self.chroot
+            .build(
+                config,
+                &prepared.dir,
+                &extra,
+                &chroot_flags,
+                &Self::makepkg_args(),
+                &env,
+                None,
+            )
+            .with_context(|| tr!("failed to rebuild '{}'", base))?;
+
+        let mut diffs = Vec::new();
+        for pkg in base.packages() {
+            let Some(first) = prepared.pkgdests.get(pkg).map(Path::new) else {
+                continue;
+            };
+            let Some(file) = first.file_name() else {
+                continue;
+            };
+            let second = dest.path().join(file);
+            for diff in repro::compare(first, &second)? {
+                diffs.push(format!("{}: {}", pkg, diff));
+            }
+        }
+
+        self.repro
+            .push((base.package_base().to_string(), diffs.is_empty()));
+        if diffs.is_empty() {
+            printtr!("{}: package is reproducible", base);
+            return Ok(());
+        }
 
-        self.add_pkg(config, base, repo, &pkgdests, &debug_paths)?;
-        self.queue_install(base, &pkgdests, &debug_paths);
-        Ok((pkgdests, version))
+        let c = config.color;
+        println!(
+            "{} {}",
+            c.warning.paint("::"),
+            c.bold.paint(tr!("'{}' is not reproducible:", base))
+        );
+        for diff in &diffs {
+            println!("    {}", diff);
+        }
+
+        match policy {
+            ReproPolicy::Warn => Ok(()),
+            ReproPolicy::Exclude => bail!(tr!("'{}' is not reproducible", base)),
+        }
     }
 
     fn queue_install(
@@ -703,6 +1173,30 @@ impl Installer {
         base: &mut Base,
         repo: Option<(&str, &str)>,
     ) -> Result<()> {
+        let prepared = self.prepare_pkgbuild(config, base, repo)?;
+        if prepared.needs_build {
+            let log = if config.build_log {
+                let log = buildlog::new_log(config, base.package_base(), &prepared.version)?;
+                self.logs
+                    .insert(base.package_base().to_string(), log.clone());
+                Some(log)
+            } else {
+                None
+            };
+            let ret = self.makepkg_pkgbuild(config, base, repo, &prepared.dir, log.as_deref());
+            post_build_command(config, base, &prepared, ret)?;
+            self.lint(config, base, &prepared)?;
+            self.verify_repro(config, base, &prepared)?;
+        }
+        self.finish_pkgbuild(config, base, repo, prepared)
+    }
+
+    fn prepare_pkgbuild(
+        &mut self,
+        config: &mut Config,
+        base: &mut Base,
+        repo: Option<(&str, &str)>,
+    ) -> Result<PreparedBuild> {
         let dir = match base {
             Base::Aur(_) => config.build_dir.join(base.package_base()),
             Base::Pkgbuild(c) => config
@@ -778,16 +1272,45 @@ impl Installer {
             ));
         }
 
-        let (mut pkgdest, version) = if build {
-            self.build_pkgbuild(config, base, repo, &dir)?
+        if build {
+            self.download_pkgbuild(config, base, repo, &dir)
         } else {
             printtr!("{}: parsing pkg list...", base);
             let (pkgdests, version) = parse_package_list(config, &dir, pkgdest)?;
-            let debug_paths = self.debug_paths(config, base, &pkgdests)?;
-            self.add_pkg(config, base, repo, &pkgdests, &debug_paths)?;
-            self.queue_install(base, &pkgdests, &debug_paths);
-            (pkgdests, version)
-        };
+            Ok(PreparedBuild {
+                dir,
+                pkgdests,
+                version,
+                needs_build: false,
+            })
+        }
+    }
+
+    fn finish_pkgbuild(
+        &mut self,
+        config: &mut Config,
+        base: &mut Base,
+        repo: Option<(&str, &str)>,
+        prepared: PreparedBuild,
+    ) -> Result<()> {
+        let PreparedBuild {
+            dir,
+            pkgdests: mut pkgdest,
+            version,
+            needs_build,
+        } = prepared;
+
+        if needs_build {
+            diskspace::record(config, base.package_base(), &dir, &pkgdest)?;
+            if !config.keep_src {
+                clean_work_dirs(&dir)?;
+            }
+        }
+
+        let debug_paths = self.debug_paths(config, base, &pkgdest)?;
+        self.add_pkg(config, base, repo, &pkgdest, &debug_paths)?;
+        self.queue_install(base, &pkgdest, &debug_paths);
+        let queued = self.install_queue.len();
 
         match &*base {
             Base::Aur(b) => {
@@ -795,6 +1318,9 @@ impl Installer {
                     if pkg.target && !self.install_targets {
                         continue;
                     }
+                    if pkg.target {
+                        self.queued_targets.push(pkg.pkg.name.to_string());
+                    }
                     self.dep_or_exp(
                         config,
                         base,
@@ -811,6 +1337,9 @@ impl Installer {
                     if pkg.target && !self.install_targets {
                         continue;
                     }
+                    if pkg.target {
+                        self.queued_targets.push(pkg.pkg.pkgname.to_string());
+                    }
                     self.dep_or_exp(
                         config,
                         base,
@@ -824,6 +1353,16 @@ impl Installer {
             }
         }
 
+        if self.install_queue.len() > queued {
+            self.installing.push(InstalledBase {
+                base: base.package_base().to_string(),
+                version: version.clone(),
+                dir,
+                target: base.target(),
+                files: self.install_queue[queued..].to_vec(),
+            });
+        }
+
         if repo.is_none() {
             if let Some(info) = self.new_devel_info.info.remove(base.package_base()) {
                 self.devel_info
@@ -837,11 +1376,223 @@ impl Installer {
         Ok(())
     }
 
+    // builds each dependency layer in parallel and installs the results before moving on
+    fn build_install_layers(
+        &mut self,
+        config: &mut Config,
+        build: &mut [Base],
+        repo: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let deps = build_deps(config, build);
+        let layers = build_layers(&deps);
+        let pkgdest = repo.map(|r| r.1);
+        let mut failed = HashSet::new();
+
+        for layer in 0..=layers.iter().copied().max().unwrap_or(0) {
+            let mut prepared = Vec::new();
+
+            for (i, &base_layer) in layers.iter().enumerate() {
+                if base_layer != layer {
+                    continue;
+                }
+                if let Some(&dep) = deps[i].iter().find(|dep| failed.contains(*dep)) {
+                    let err = anyhow!(tr!(
+                        "not building '{}' as '{}' failed",
+                        build[i],
+                        build[dep]
+                    ));
+                    print_error(config.color.error, err);
+                    self.failed.push(build[i].clone());
+                    failed.insert(i);
+                    continue;
+                }
+                let base = &mut build[i];
+                match self.prepare_pkgbuild(config, base, repo) {
+                    Ok(p) => prepared.push((i, p)),
+                    Err(e) => {
+                        if config.fail_fast {
+                            return Err(e);
+                        }
+                        print_error(config.color.error, e);
+                        self.failed.push(base.clone());
+                        failed.insert(i);
+                    }
+                }
+            }
+
+            let mut cmds = Vec::new();
+            let mut logs = Vec::new();
+            for (i, p) in &prepared {
+                if !p.needs_build {
+                    continue;
+                }
+                let base = &build[*i];
+                let log = buildlog::new_log(config, base.package_base(), &p.version)?;
+                self.logs
+                    .insert(base.package_base().to_string(), log.clone());
+                let file = File::create(&log)
+                    .with_context(|| tr!("failed to create log file: {}", log.display()))?;
+
+                let args = Self::makepkg_args();
+                let cmd = exec::new_makepkg(config, &p.dir, &args, pkgdest);
+                let mut cmd = limit_build(config, cmd)?;
+                cmd.stdin(Stdio::null())
+                    .stdout(file.try_clone()?)
+                    .stderr(file);
+
+                printtr!("{}: building, logging to {}", base, log.display());
+                cmds.push(cmd);
+                logs.push((*i, log));
+            }
+
+            let mut results = exec::command_status_jobs(cmds, config.build_jobs).into_iter();
+
+            for (i, log) in logs {
+                let base = &build[i];
+                let ret = results
+                    .next()
+                    .unwrap()
+                    .and_then(|s| s.success().map_err(Into::into));
+                let (_, p) = prepared.iter().find(|(j, _)| *j == i).unwrap();
+                let ret = post_build_command(config, base, p, ret)
+                    .with_context(|| tr!("failed to build '{}' (log: {})", base, log.display()))
+                    .and_then(|()| self.lint(config, base, p));
+                if let Err(e) = ret {
+                    if config.fail_fast {
+                        return Err(e);
+                    }
+                    print_error(config.color.error, e);
+                    self.failed.push(base.clone());
+                    failed.insert(i);
+                }
+            }
+
+            for (i, p) in prepared {
+                if failed.contains(&i) {
+                    continue;
+                }
+                let base = &mut build[i];
+                match self.finish_pkgbuild(config, base, repo, p) {
+                    Ok(()) => self.set_built(config, base)?,
+                    Err(e) => {
+                        if config.fail_fast {
+                            return Err(e);
+                        }
+                        print_error(config.color.error, e);
+                        self.failed.push(base.clone());
+                        failed.insert(i);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn post_install_command(&mut self, config: &Config, success: bool) -> Result<()> {
+        let installing = std::mem::take(&mut self.installing);
+        let Some(hook) = &config.post_install_command else {
+            return Ok(());
+        };
+
+        for base in installing {
+            let env = [
+                ("PKGBASE", base.base.clone()),
+                ("VERSION", base.version),
+                ("PKGFILES", base.files.join(" ")),
+                ("TARGET", (base.target as u8).to_string()),
+                ("SUCCESS", (success as u8).to_string()),
+            ];
+            let ret = run_hook(hook, &base.dir, &env)
+                .with_context(|| tr!("PostInstallCommand failed for '{}'", base.base));
+            if let Err(e) = ret {
+                if config.fail_fast {
+                    return Err(e);
+                }
+                print_error(config.color.error, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_built(&mut self, config: &Config, base: &Base) -> Result<()> {
+        if let Some(journal) = &mut self.journal {
+            journal.set_built(config, base.package_base())?;
+        }
+        Ok(())
+    }
+
+    // runs the linter on the PKGBUILD and the built packages
+    fn lint(&mut self, config: &Config, base: &Base, prepared: &PreparedBuild) -> Result<()> {
+        let Some(policy) = config.lint else {
+            return Ok(());
+        };
+
+        let mut cmd = Command::new(&config.lint_bin);
+        cmd.args(&config.lint_flags)
+            .arg(prepared.dir.join("PKGBUILD"))
+            .args(base.packages().filter_map(|p| prepared.pkgdests.get(p)));
+        let output = exec::command_output(&mut cmd)?;
+        if !output.status.success() {
+            bail!(
+                "{}: {}",
+                tr!("failed to lint '{}'", base),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let findings = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        self.lints
+            .push((base.package_base().to_string(), findings.len()));
+        if findings.is_empty() {
+            return Ok(());
+        }
+
+        let c = config.color;
+        println!(
+            "{} {}",
+            c.warning.paint("::"),
+            c.bold.paint(tr!("Lint findings for '{}':", base))
+        );
+        for finding in &findings {
+            println!("    {}", finding);
+        }
+
+        match policy {
+            LintPolicy::Warn => Ok(()),
+            LintPolicy::Ask if ask(config, &tr!("Continue with '{}'?", base), true) => Ok(()),
+            LintPolicy::Ask | LintPolicy::Fail => bail!(tr!("lint checks failed for '{}'", base)),
+        }
+    }
+
     async fn build_install_pkgbuilds(
         &mut self,
         config: &mut Config,
         build: &mut [Base],
     ) -> Result<()> {
+        ensure!(
+            config.verify_repro.is_none() || config.chroot,
+            tr!("--verify-repro requires --chroot")
+        );
+
+        if self.journal.is_none() {
+            if Journal::exists(config) {
+                eprintln!(
+                    "{} {}",
+                    config.color.warning.paint(tr!("warning:")),
+                    tr!("discarding the interrupted transaction, it can no longer be resumed")
+                );
+            }
+            let journal = Journal::new(config, build, self.install_targets, &self.remove_make)?;
+            journal.save(config)?;
+            self.journal = Some(journal);
+        }
+
         if config.devel {
             printtr!("fetching devel info...");
             self.devel_info = load_devel_info(config)?.unwrap_or_default();
@@ -853,6 +1604,8 @@ impl Installer {
         if let Some(repo) = default_repo {
             let file = repo::file(repo).unwrap();
             repo::init(config, file, repo.name())?;
+            // makepkg writes the packages straight into the repo
+            chown_build_user(config, Path::new(file))?;
         }
 
         if config.chroot {
@@ -867,6 +1620,28 @@ impl Installer {
             default_repo.map(|r| (r.name().to_string(), repo::file(r).unwrap().to_string()));
         drop(repo);
 
+        diskspace::check(
+            config,
+            build,
+            repo_server
+                .as_ref()
+                .map(|(name, file)| (name.as_str(), file.as_str())),
+        )?;
+
+        if config.build_jobs > 1 && config.chroot {
+            eprintln!(
+                "{} {}",
+                config.color.warning.paint(tr!("warning:")),
+                tr!("BuildJobs has no effect when building in a chroot")
+            );
+        } else if config.build_jobs > 1 {
+            let repo_server = repo_server
+                .as_ref()
+                .map(|(name, file)| (name.as_str(), file.as_str()));
+            self.build_install_layers(config, build, repo_server)?;
+            return self.do_install(config);
+        }
+
         for base in build {
             self.failed.push(base.clone());
             let repo_server = repo_server
@@ -878,6 +1653,7 @@ impl Installer {
             match err {
                 Ok(_) => {
                     self.failed.pop().unwrap();
+                    self.set_built(config, base)?;
                 }
                 Err(e) => {
                     if config.fail_fast {
@@ -897,9 +1673,37 @@ impl Installer {
     }
 
     pub async fn install(&mut self, config: &mut Config, targets_str: &[String]) -> Result<()> {
+        if config.print_plan.is_some() {
+            // printing the plan must not change the system, refreshing included
+            ensure!(
+                !config.args.has_arg("y", "refresh"),
+                tr!("--print-plan can not be used with --refresh")
+            );
+            // only the plan goes to stdout and nothing may prompt
+            config.no_confirm = true;
+            config.combined_upgrade = true;
+            config.upgrade_menu = false;
+            config.news_on_upgrade = false;
+            config.sudo_loop.clear();
+            self.plan_stdout = Some(redirect_to_stderr()?);
+        }
+
         self.sudo_loop(config)?;
         self.news(config).await?;
 
+        if self.plan_stdout.is_some() {
+            return self.transaction(config, targets_str).await;
+        }
+
+        let snapshot = Snapshot::new(config)?;
+        let err = self.transaction(config, targets_str).await;
+        if let Err(e) = snapshot.record(config) {
+            print_error(config.color.error, e);
+        }
+        err
+    }
+
+    async fn transaction(&mut self, config: &mut Config, targets_str: &[String]) -> Result<()> {
         config.set_op_args_globals(Op::Sync);
         config.targets = targets_str.to_vec();
         config.args.targets = config.targets.clone();
@@ -956,7 +1760,16 @@ impl Installer {
 
         let repos = config.pkgbuild_repos.clone();
         let repos = repos.aur_depends_repo(config);
-        let mut resolver = resolver(config, &config.alpm, &config.raur, &mut cache, repos, flags);
+        let providers = Providers::new(config);
+        let mut resolver = resolver(
+            config,
+            providers,
+            &config.alpm,
+            &config.raur,
+            &mut cache,
+            repos,
+            flags,
+        );
 
         if self.sysupgrade != 0 {
             // TODO?
@@ -972,20 +1785,35 @@ impl Installer {
             self.upgrades = upgrades;
         }
 
+        let upgrades = self
+            .upgrades
+            .aur_keep
+            .iter()
+            .map(|p| Targ {
+                repo: Some(config.aur_namespace()),
+                pkg: p,
+            })
+            .chain(self.upgrades.pkgbuild_keep.iter().map(|(repo, pkg)| Targ {
+                repo: Some(repo),
+                pkg,
+            }))
+            .collect::<Vec<_>>();
+        let picked = split::pick(config, aur_targets, &upgrades).await?;
         let mut targets = repo_targets.to_vec();
-        targets.extend(aur_targets);
-        targets.extend(self.upgrades.aur_keep.iter().map(|p| Targ {
-            repo: Some(config.aur_namespace()),
-            pkg: p,
-        }));
-        targets.extend(self.upgrades.pkgbuild_keep.iter().map(|(repo, pkg)| Targ {
-            repo: Some(repo),
-            pkg,
-        }));
+        targets.extend(
+            aur_targets
+                .iter()
+                .filter(|t| !picked.drop.contains(t.pkg))
+                .copied(),
+        );
+        targets.extend(picked.targets());
+        targets.extend(upgrades);
 
         targets.extend(self.upgrades.repo_keep.iter().map(Targ::from));
 
-        if self.shoud_just_pacman(config.mode, aur_targets, &self.upgrades, self.ran_pacman) {
+        if self.plan_stdout.is_none()
+            && self.shoud_just_pacman(config.mode, aur_targets, &self.upgrades, self.ran_pacman)
+        {
             print_warnings(config, &cache, None);
             let mut args = config.pacman_args();
             let targets = targets.iter().map(|t| t.to_string()).collect::<Vec<_>>();
@@ -1002,7 +1830,7 @@ impl Installer {
             return Ok(());
         }
 
-        if targets.is_empty() && !self.upgrade_later(config) {
+        if targets.is_empty() && !self.upgrade_later(config) && self.plan_stdout.is_none() {
             print_warnings(config, &cache, None);
             if !self.done_something || self.sysupgrade != 0 {
                 printtr!(" there is nothing to do");
@@ -1018,6 +1846,15 @@ impl Installer {
 
         let mut actions = resolver.resolve_targets(&targets).await?;
         debug!("{:#?}", actions);
+
+        if let (Some(stdout), Some(format)) = (&self.plan_stdout, config.print_plan) {
+            reopen_stdout(stdout)?;
+            plan::print_plan(config, format, &actions, &self.upgrades.devel)?;
+            if !actions.missing.is_empty() {
+                return Status::err(1);
+            }
+            return Ok(());
+        }
         let repo_targs = actions
             .install
             .iter()
@@ -1035,17 +1872,57 @@ impl Installer {
             err = self.build_install_pkgbuilds(config, &mut build).await;
         }
 
+        self.finish_transaction(config, &build, &repo_targs, err)
+    }
+
+    fn finish_transaction(
+        &mut self,
+        config: &Config,
+        build: &[Base],
+        repo_targs: &[String],
+        mut err: Result<()>,
+    ) -> Result<()> {
         if err.is_ok() && config.chroot {
             if config.repos == LocalRepos::None {
-                err = self.chroot_install(config, &[], &repo_targs);
+                err = self.chroot_install(config, &[], repo_targs);
                 self.do_install(config)?;
             } else {
-                err = self.chroot_install(config, &build, &repo_targs);
+                err = self.chroot_install(config, build, repo_targs);
+                self.post_install_command(config, err.is_ok())?;
+                if let (Ok(()), Some(journal)) = (&err, &mut self.journal) {
+                    journal.set_installed(config)?;
+                }
             }
         }
 
-        self.build_cleanup(config, &build)?;
-        err
+        if err.is_ok() {
+            self.installed_targets.extend(repo_targs.iter().cloned());
+            // chroot builds are installed by chroot_install instead of do_install
+            if config.chroot {
+                self.installed_targets.append(&mut self.queued_targets);
+            }
+        }
+
+        if err.is_ok() && self.failed.is_empty() && self.journal.take().is_some() {
+            Journal::remove(config)?;
+        }
+
+        if !self.failed.is_empty() {
+            Failed::new(config, &self.failed, &config.targets).save(config)?;
+        } else if err.is_ok() {
+            Failed::remove(config)?;
+        }
+
+        let err = self.build_cleanup(config, build).and(err);
+        let hook = post_transaction_command(config, build, &self.failed, err.is_ok());
+        match (err, hook) {
+            (Ok(()), hook) => hook,
+            (err, Ok(())) => err,
+            (err, Err(e)) => {
+                print_error(config.color.error, e);
+                err
+            }
+        }
     }
 
     fn shoud_just_pacman(
@@ -1073,8 +1950,11 @@ impl Installer {
         cache: &Cache,
         actions: &mut Actions<'_>,
     ) -> Result<()> {
-        if !actions.build.is_empty() && nix::unistd::getuid().is_root() {
-            bail!(tr!("can't install AUR package as root"));
+        if !actions.build.is_empty() {
+            if nix::unistd::getuid().is_root() && config.build_user().is_none() {
+                bail!(tr!("can't install AUR package as root"));
+            }
+            chown_build_user(config, &config.build_dir)?;
         }
         if !actions.build.is_empty() && config.args.has_arg("w", "downloadonly") {
             bail!(tr!("--downloadonly can't be used for AUR packages"));
@@ -1541,7 +2421,7 @@ fn asexp<S: AsRef<str>>(config: &Config, pkgs: &[S]) -> Result<()> {
     set_install_reason(config, "asexplicit", pkgs)
 }
 
-fn set_install_reason<S: AsRef<str>>(config: &Config, reason: &str, pkgs: &[S]) -> Result<()> {
+pub fn set_install_reason<S: AsRef<str>>(config: &Config, reason: &str, pkgs: &[S]) -> Result<()> {
     let alpm = config.new_alpm()?;
     let db = alpm.localdb();
 
@@ -1565,19 +2445,160 @@ fn set_install_reason<S: AsRef<str>>(config: &Config, reason: &str, pkgs: &[S])
     Ok(())
 }
 
-fn pre_build_command(config: &Config, dir: &Path, base: &str, version: &str) -> Result<()> {
-    if let Some(ref pb_cmd) = config.pre_build_command {
-        let mut cmd = Command::new("sh");
-        cmd.env("PKGBASE", base)
-            .env("VERSION", version)
-            .current_dir(dir)
-            .arg("-c")
-            .arg(pb_cmd);
+// true when anything under path is owned by someone else
+fn needs_chown(path: &Path, uid: u32) -> bool {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return false;
+    };
+    if metadata.uid() != uid {
+        return true;
+    }
+
+    metadata.is_dir()
+        && read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|e| needs_chown(&e.path(), uid))
+}
+
+// the build user has to own the directories it clones, builds and edits in
+fn chown_build_user(config: &Config, dir: &Path) -> Result<()> {
+    let Some(name) = config.build_user() else {
+        return Ok(());
+    };
+    let user = User::from_name(name)?.with_context(|| tr!("unknown user: {}", name))?;
+
+    create_dir_all(dir).with_context(|| tr!("failed to create directory: {}", dir.display()))?;
+    if needs_chown(dir, user.uid.as_raw()) {
+        let mut cmd = Command::new("chown");
+        cmd.arg("-R")
+            .arg(format!("{}:{}", user.uid, user.gid))
+            .arg(dir);
         exec::command(&mut cmd)?;
     }
+
+    // owning the directory is no use when a parent can't be entered
+    let mut cmd = exec::build_user_command(config, "test");
+    cmd.arg("-w").arg(dir).arg("-a").arg("-x").arg(dir);
+    if !cmd.status().is_ok_and(|s| s.success()) {
+        bail!(tr!(
+            "build user '{user}' can not access {dir}",
+            user = name,
+            dir = dir.display()
+        ));
+    }
+    Ok(())
+}
+
+fn base_repo(base: &Base) -> Option<&str> {
+    match base {
+        Base::Aur(_) => None,
+        Base::Pkgbuild(base) => Some(base.repo.as_str()),
+    }
+}
+
+// the same SOURCE_DATE_EPOCH is needed for both builds when verifying reproducibility
+fn source_date_epoch(config: &Config, dir: &Path) -> Result<Option<(String, String)>> {
+    if config.verify_repro.is_none() || config.env.iter().any(|(k, _)| k == "SOURCE_DATE_EPOCH") {
+        return Ok(None);
+    }
+
+    let pkgbuild = dir.join("PKGBUILD");
+    let time = pkgbuild
+        .metadata()
+        .and_then(|m| m.modified())
+        .with_context(|| tr!("failed to read file: {}", pkgbuild.display()))?;
+    let epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Ok(Some(("SOURCE_DATE_EPOCH".to_string(), epoch.to_string())))
+}
+
+fn run_hook(hook: &str, dir: &Path, env: &[(&str, String)]) -> Result<()> {
+    let mut cmd = Command::new("sh");
+    cmd.envs(env.iter().map(|(k, v)| (k, v)))
+        .current_dir(dir)
+        .arg("-c")
+        .arg(hook);
+    exec::command(&mut cmd)
+}
+
+fn pre_build_command(config: &Config, dir: &Path, base: &str, version: &str) -> Result<()> {
+    let Some(hook) = &config.pre_build_command else {
+        return Ok(());
+    };
+
+    let env = [
+        ("PKGBASE", base.to_string()),
+        ("VERSION", version.to_string()),
+    ];
+    run_hook(hook, dir, &env).with_context(|| tr!("PreBuildCommand failed for '{}'", base))
+}
+
+// what makepkg -c removes after a successful build
+fn clean_work_dirs(dir: &Path) -> Result<()> {
+    for path in [dir.join("src"), dir.join("pkg")] {
+        match remove_dir_all(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e).with_context(|| tr!("failed to remove: {}", path.display()))
+            }
+            _ => (),
+        }
+    }
     Ok(())
 }
 
+// the build's own error takes priority over the hook's
+fn post_build_command(
+    config: &Config,
+    base: &Base,
+    prepared: &PreparedBuild,
+    ret: Result<()>,
+) -> Result<()> {
+    let Some(hook) = &config.post_build_command else {
+        return ret;
+    };
+
+    let files = base
+        .packages()
+        .filter_map(|p| prepared.pkgdests.get(p))
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>();
+    let env = [
+        ("PKGBASE", base.package_base().to_string()),
+        ("VERSION", prepared.version.clone()),
+        ("PKGFILES", files.join(" ")),
+        ("TARGET", (base.target() as u8).to_string()),
+        ("SUCCESS", (ret.is_ok() as u8).to_string()),
+    ];
+    let hook = run_hook(hook, &prepared.dir, &env)
+        .with_context(|| tr!("PostBuildCommand failed for '{}'", base));
+    ret.and(hook)
+}
+
+fn post_transaction_command(
+    config: &Config,
+    build: &[Base],
+    failed: &[Base],
+    success: bool,
+) -> Result<()> {
+    let Some(hook) = &config.post_transaction_command else {
+        return Ok(());
+    };
+
+    let bases = build.iter().map(|b| b.package_base()).collect::<Vec<_>>();
+    let failed = failed.iter().map(|b| b.package_base()).collect::<Vec<_>>();
+    let env = [
+        ("PKGBASES", bases.join(" ")),
+        ("FAILED", failed.join(" ")),
+        ("SUCCESS", (success as u8).to_string()),
+    ];
+    let dir = std::env::current_dir()?;
+    run_hook(hook, &dir, &env).context(tr!("PostTransactionCommand failed"))
+}
+
 fn file_manager(
     config: &Config,
     fetch: &aur_fetch::Fetch,
@@ -1593,7 +2614,8 @@ fn file_manager(
 }
 
 fn run_file_manager(config: &Config, fm: &str, dir: &Path) -> Result<()> {
-    let mut cmd = Command::new(fm);
+    chown_build_user(config, dir)?;
+    let mut cmd = exec::build_user_command(config, fm);
     cmd.args(&config.fm_flags).arg(dir).current_dir(dir);
     let ret =
         command_status(&mut cmd).with_context(|| tr!("failed to execute file manager: {}", fm))?;
@@ -1884,6 +2906,76 @@ fn check_deps_sync<'a>(
     }
 }
 
+// the earlier bases in the build order that each base depends on
+fn build_deps(config: &Config, build: &[Base]) -> Vec<Vec<usize>> {
+    let arch = config.alpm.architectures().first().unwrap_or_default();
+    let mut deps_of = Vec::with_capacity(build.len());
+
+    for (i, base) in build.iter().enumerate() {
+        let deps: Vec<&str> = match base {
+            Base::Aur(base) => base
+                .pkgs
+                .iter()
+                .flat_map(|p| {
+                    let check = (!config.no_check).then_some(&p.pkg.check_depends);
+                    p.pkg
+                        .depends
+                        .iter()
+                        .chain(&p.pkg.make_depends)
+                        .chain(check.into_iter().flatten())
+                })
+                .map(|s| s.as_str())
+                .collect(),
+            Base::Pkgbuild(base) => {
+                let check = (!config.no_check).then_some(&base.srcinfo.base.checkdepends);
+                supported_deps(config, &base.srcinfo.base.makedepends)
+                    .chain(check.into_iter().flat_map(|d| supported_deps(config, d)))
+                    .chain(
+                        base.pkgs
+                            .iter()
+                            .flat_map(|p| supported_deps(config, &p.pkg.depends)),
+                    )
+                    .collect()
+            }
+        };
+
+        let provides_dep = |other: &Base| {
+            deps.iter().any(|dep| match other {
+                Base::Aur(other) => other.pkgs.iter().any(|p| {
+                    let provides = p.pkg.provides.iter().map(|p| Depend::new(p.as_str()));
+                    satisfies_nover(Depend::new(*dep), &p.pkg.name, provides)
+                }),
+                Base::Pkgbuild(other) => other.pkgs.iter().any(|p| {
+                    let provides = p.pkg.provides.arch(arch).map(Depend::new);
+                    satisfies_nover(Depend::new(*dep), &p.pkg.pkgname, provides)
+                }),
+            })
+        };
+
+        let deps = build[..i]
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| provides_dep(*other))
+            .map(|(j, _)| j)
+            .collect();
+        deps_of.push(deps);
+    }
+
+    deps_of
+}
+
+// splits the build order into layers where each base only depends on bases in earlier layers
+fn build_layers(deps: &[Vec<usize>]) -> Vec<usize> {
+    let mut layers: Vec<usize> = Vec::with_capacity(deps.len());
+
+    for deps in deps {
+        let layer = deps.iter().map(|&j| layers[j] + 1).max().unwrap_or(0);
+        layers.push(layer);
+    }
+
+    layers
+}
+
 fn supported_deps<'a>(config: &'a Config, deps: &'a ArchVecs) -> impl Iterator<Item = &'a str> {
     let arch = config.alpm.architectures().first().unwrap_or_default();
     deps.arch(arch)
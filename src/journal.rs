@@ -0,0 +1,187 @@
+use crate::config::Config;
+use crate::util::{load_state, save_state, with_fresh_localdb};
+
+use std::collections::BTreeMap;
+use std::fs::remove_file;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use aur_depends::Base;
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalPkg {
+    pub name: String,
+    pub make: bool,
+    pub target: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalBase {
+    pub pkgbase: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    pub version: String,
+    pub build: bool,
+    pub built: bool,
+    pub installed: bool,
+    pub pkgs: Vec<JournalPkg>,
+}
+
+/// The state of an in progress build transaction, saved so it can be
+/// continued with --resume.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Journal {
+    pub install_targets: bool,
+    pub remove_make: Vec<String>,
+    pub bases: Vec<JournalBase>,
+    pub local: BTreeMap<String, String>,
+}
+
+impl JournalBase {
+    fn new(base: &Base) -> Self {
+        let (repo, pkgs) = match base {
+            Base::Aur(base) => {
+                let pkgs = base
+                    .pkgs
+                    .iter()
+                    .map(|p| JournalPkg {
+                        name: p.pkg.name.clone(),
+                        make: p.make,
+                        target: p.target,
+                    })
+                    .collect();
+                (None, pkgs)
+            }
+            Base::Pkgbuild(base) => {
+                let pkgs = base
+                    .pkgs
+                    .iter()
+                    .map(|p| JournalPkg {
+                        name: p.pkg.pkgname.clone(),
+                        make: p.make,
+                        target: p.target,
+                    })
+                    .collect();
+                (Some(base.repo.clone()), pkgs)
+            }
+        };
+
+        JournalBase {
+            pkgbase: base.package_base().to_string(),
+            repo,
+            version: base.version(),
+            build: base.build(),
+            built: false,
+            installed: false,
+            pkgs,
+        }
+    }
+}
+
+impl Journal {
+    pub fn new(
+        config: &Config,
+        build: &[Base],
+        install_targets: bool,
+        remove_make: &[String],
+    ) -> Result<Self> {
+        Ok(Journal {
+            install_targets,
+            remove_make: remove_make.to_vec(),
+            bases: build.iter().map(JournalBase::new).collect(),
+            local: local_pkgs(config)?,
+        })
+    }
+
+    pub fn path(config: &Config) -> PathBuf {
+        config.state_dir.join("transaction.toml")
+    }
+
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        load_state(&config.state_dir, "transaction.toml")
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        save_state(&config.state_dir, "transaction.toml", self)
+    }
+
+    pub fn exists(config: &Config) -> bool {
+        Self::path(config).exists()
+    }
+
+    pub fn remove(config: &Config) -> Result<()> {
+        let path = Self::path(config);
+        match remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| tr!("failed to remove: {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_built(&mut self, config: &Config, pkgbase: &str) -> Result<()> {
+        if let Some(base) = self.bases.iter_mut().find(|b| b.pkgbase == pkgbase) {
+            base.built = true;
+        }
+        self.save(config)
+    }
+
+    // everything built so far has been installed by the time this is called
+    pub fn set_installed(&mut self, config: &Config) -> Result<()> {
+        if !self.bases.iter().any(|b| b.built && !b.installed) {
+            return Ok(());
+        }
+        for base in &mut self.bases {
+            base.installed |= base.built;
+        }
+        self.local = local_pkgs(config)?;
+        self.save(config)
+    }
+
+    pub fn check_local(&self, config: &Config) -> Result<()> {
+        let local = local_pkgs(config)?;
+        let mut changed = local
+            .iter()
+            .filter(|(name, ver)| self.local.get(*name) != Some(*ver))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        changed.extend(
+            self.local
+                .keys()
+                .filter(|name| !local.contains_key(*name))
+                .map(|name| name.as_str()),
+        );
+
+        if !changed.is_empty() {
+            changed.sort_unstable();
+            bail!(tr!(
+                "the local database has changed since the transaction was saved: {}",
+                changed.join("  ")
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Throws away the saved transaction for --resume=abort.
+pub fn abort(config: &Config) -> Result<i32> {
+    if !Journal::exists(config) {
+        bail!(tr!("there is no transaction to resume"));
+    }
+    Journal::remove(config)?;
+    printtr!("discarded the saved transaction");
+    Ok(0)
+}
+
+fn local_pkgs(config: &Config) -> Result<BTreeMap<String, String>> {
+    with_fresh_localdb(config, |db| {
+        db.pkgs()
+            .iter()
+            .map(|p| (p.name().to_string(), p.version().to_string()))
+            .collect()
+    })
+}
@@ -1,27 +1,42 @@
 mod args;
+mod buildlog;
 mod chroot;
 mod clean;
 mod command_line;
 mod completion;
 mod config;
 mod devel;
+mod diskspace;
 mod download;
 mod exec;
+mod export;
 mod fmt;
 mod help;
+mod history;
 mod info;
 mod install;
+mod journal;
 mod keys;
+mod limit;
+mod lock;
+mod manifest;
 mod news;
+mod optdeps;
 mod order;
 mod pkgbuild;
+mod plan;
+mod provider;
 mod query;
 mod remove;
 mod repo;
+mod repro;
+mod retry;
 mod search;
+mod split;
 mod stats;
 mod sync;
 mod upgrade;
+mod upstream;
 mod util;
 
 #[cfg(feature = "mock")]
@@ -37,7 +52,7 @@ type RaurHandle = crate::mock::Mock;
 extern crate smart_default;
 
 use crate::chroot::Chroot;
-use crate::config::{Config, Op};
+use crate::config::{Config, Op, Resume};
 use crate::exec::has_command;
 use crate::query::print_upgrade_list;
 
@@ -183,9 +198,10 @@ async fn run2<S: AsRef<str>>(config: &mut Config, args: &[S]) -> Result<i32> {
         config.aur_url.clone()
     };
 
+    let (git, git_flags) = config.clone_git();
     config.fetch = aur_fetch::Fetch {
-        git: config.git_bin.clone().into(),
-        git_flags: config.git_flags.clone(),
+        git: git.into(),
+        git_flags,
         clone_dir: config.build_dir.clone(),
         diff_dir: config.cache_dir.join("diff"),
         aur_url,
@@ -290,6 +306,36 @@ async fn handle_default(config: &mut Config) -> Result<i32> {
     if config.gendb {
         devel::gendb(config).await?;
         Ok(0)
+    } else if config.devel_status {
+        devel::devel_status(config)
+    } else if config.rollback.is_some() {
+        config.need_root = true;
+        history::rollback(config)
+    } else if config.retry_failed {
+        config.need_root = true;
+        retry::retry_failed(config).await
+    } else if let Some(path) = &config.write_lock {
+        lock::write_lock(config, path)
+    } else if let Some(path) = config.apply.clone() {
+        config.need_root = true;
+        manifest::apply(config, &path).await
+    } else if let Some(path) = &config.export {
+        export::export(config, path)
+    } else if let Some(path) = config.import.clone() {
+        config.need_root = true;
+        export::import(config, &path).await
+    } else if config.show_log {
+        buildlog::show_log(config)
+    } else if config.list_providers {
+        provider::list(config)
+    } else if config.reset_providers {
+        provider::reset(config)
+    } else if config.resume == Some(Resume::Abort) {
+        journal::abort(config)
+    } else if config.resume.is_some() {
+        config.need_root = true;
+        install::resume(config).await?;
+        Ok(0)
     } else if config.clean > 0 {
         config.need_root = true;
         let unneeded = util::unneeded_pkgs(config, !config.optional);
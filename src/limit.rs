@@ -0,0 +1,309 @@
+use crate::config::Config;
+
+use std::fmt;
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir, write, File};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use nix::unistd::Uid;
+use tr::tr;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+// cpu.max is written as the quota per period in microseconds
+const CPU_PERIOD: u64 = 100000;
+
+// the command with the given program and args in front of it
+fn wrap(mut prefix: Command, cmd: &Command) -> Command {
+    prefix.arg(cmd.get_program()).args(cmd.get_args());
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => prefix.env(key, value),
+            None => prefix.env_remove(key),
+        };
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        prefix.current_dir(dir);
+    }
+    prefix
+}
+
+/// The memory limit of a build, in systemd's MemoryMax= syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryMax {
+    Bytes(u64),
+    Percent(u64),
+    Infinity,
+}
+
+impl FromStr for MemoryMax {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || tr!("invalid value for '{}': {}", "BuildMemoryMax", value);
+
+        if value == "infinity" {
+            return Ok(MemoryMax::Infinity);
+        }
+
+        if let Some(percent) = value.strip_suffix('%') {
+            let percent: u64 = percent.parse().with_context(invalid)?;
+            ensure!(percent <= 100, invalid());
+            return Ok(MemoryMax::Percent(percent));
+        }
+
+        let (num, shift) = match value.char_indices().last() {
+            Some((i, 'K')) => (&value[..i], 10),
+            Some((i, 'M')) => (&value[..i], 20),
+            Some((i, 'G')) => (&value[..i], 30),
+            Some((i, 'T')) => (&value[..i], 40),
+            _ => (value, 0),
+        };
+        let num: u64 = num.parse().with_context(invalid)?;
+        let bytes = num.checked_mul(1u64 << shift).with_context(invalid)?;
+        Ok(MemoryMax::Bytes(bytes))
+    }
+}
+
+impl fmt::Display for MemoryMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryMax::Bytes(bytes) => write!(f, "{}", bytes),
+            MemoryMax::Percent(percent) => write!(f, "{}%", percent),
+            MemoryMax::Infinity => f.write_str("infinity"),
+        }
+    }
+}
+
+impl MemoryMax {
+    // the value for memory.max
+    fn cgroup_value(self) -> Result<String> {
+        let percent = match self {
+            MemoryMax::Bytes(bytes) => return Ok(bytes.to_string()),
+            MemoryMax::Percent(percent) => percent,
+            MemoryMax::Infinity => return Ok("max".to_string()),
+        };
+
+        let meminfo = read_to_string("/proc/meminfo")?;
+        let total = meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix("MemTotal:"))
+            .and_then(|l| l.trim().strip_suffix("kB"))
+            .context(tr!("failed to read total memory"))?
+            .trim()
+            .parse::<u64>()?;
+        let bytes = total
+            .checked_mul(1024 * percent)
+            .with_context(|| tr!("invalid value for '{}': {}", "BuildMemoryMax", self))?;
+        Ok((bytes / 100).to_string())
+    }
+}
+
+/// The CPU limit of a build as a percentage of one CPU, in systemd's
+/// CPUQuota= syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuQuota(u64);
+
+impl FromStr for CpuQuota {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || tr!("invalid value for '{}': {}", "BuildCPUQuota", value);
+
+        let Some(percent) = value.strip_suffix('%') else {
+            bail!(invalid());
+        };
+        let percent: u64 = percent.parse().with_context(invalid)?;
+        ensure!(
+            percent > 0 && percent.checked_mul(CPU_PERIOD).is_some(),
+            invalid()
+        );
+        Ok(CpuQuota(percent))
+    }
+}
+
+impl fmt::Display for CpuQuota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl CpuQuota {
+    // the value for cpu.max, checked for overflow when parsed
+    fn cgroup_value(self) -> String {
+        format!("{} {}", CPU_PERIOD * self.0 / 100, CPU_PERIOD)
+    }
+}
+
+fn systemd_run_args() -> Vec<&'static str> {
+    let mut args = Vec::new();
+    if !Uid::current().is_root() {
+        args.push("--user");
+    }
+    args.extend(["--scope", "--quiet", "--collect"]);
+    args
+}
+
+// systemd-run can be installed without a usable systemd, e.g. in a container
+// or without a user session, so check that it can actually start a scope
+static SYSTEMD_RUN: LazyLock<bool> = LazyLock::new(|| {
+    Command::new("systemd-run")
+        .args(systemd_run_args())
+        .arg("true")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+});
+
+fn systemd_run(config: &Config) -> Command {
+    let mut cmd = Command::new("systemd-run");
+    cmd.args(systemd_run_args());
+    if let Some(mem) = &config.build_memory_max {
+        cmd.arg("-p").arg(format!("MemoryMax={}", mem));
+    }
+    if let Some(cpu) = &config.build_cpu_quota {
+        cmd.arg("-p").arg(format!("CPUQuota={}", cpu));
+    }
+    cmd.arg("--");
+    cmd
+}
+
+// our own cgroup with the memory and cpu controllers enabled for its children,
+// only usable when they have been delegated to us, e.g. inside a container with
+// its own cgroup namespace
+static DELEGATED_CGROUP: LazyLock<Result<Option<PathBuf>, String>> =
+    LazyLock::new(|| delegate().map_err(|e| format!("{:#}", e)));
+
+// the number of build cgroups created so far
+static BUILD_CGROUPS: AtomicUsize = AtomicUsize::new(0);
+
+fn delegate() -> Result<Option<PathBuf>> {
+    let Ok(cgroup) = read_to_string("/proc/self/cgroup") else {
+        return Ok(None);
+    };
+    let Some(path) = cgroup.lines().find_map(|l| l.strip_prefix("0::")) else {
+        return Ok(None);
+    };
+    let parent = PathBuf::from(CGROUP_ROOT).join(path.trim_start_matches('/'));
+
+    let subtree = parent.join("cgroup.subtree_control");
+    let Ok(enabled) = read_to_string(&subtree) else {
+        return Ok(None);
+    };
+    let enabled = enabled.split_whitespace().collect::<Vec<_>>();
+    if !enabled.contains(&"memory") || !enabled.contains(&"cpu") {
+        let available = read_to_string(parent.join("cgroup.controllers")).unwrap_or_default();
+        let available = available.split_whitespace().collect::<Vec<_>>();
+        let procs = read_to_string(parent.join("cgroup.procs")).unwrap_or_default();
+        let pid = std::process::id().to_string();
+        // controllers can only be enabled for the children of a cgroup without
+        // processes, so this only works when paru is alone in its cgroup
+        if !available.contains(&"memory")
+            || !available.contains(&"cpu")
+            || procs.lines().any(|p| p != pid)
+        {
+            return Ok(None);
+        }
+
+        // move paru out of the way and back again if the controllers still
+        // can not be enabled, e.g. when we are not at the root of the delegated tree
+        let leaf = parent.join("paru");
+        create_dir_all(&leaf).with_context(|| tr!("failed to create {}", leaf.display()))?;
+        let procs = leaf.join("cgroup.procs");
+        write(&procs, "0").with_context(|| tr!("failed to write to {}", procs.display()))?;
+        if let Err(e) = write(&subtree, "+memory +cpu") {
+            let _ = write(parent.join("cgroup.procs"), "0");
+            let _ = remove_dir(&leaf);
+            return Err(e).with_context(|| tr!("failed to write to {}", subtree.display()));
+        }
+    }
+
+    // clean up the build cgroups of earlier runs, the ones still in use are kept
+    for entry in read_dir(&parent)
+        .with_context(|| tr!("failed to read {}", parent.display()))?
+        .flatten()
+    {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with("paru-build-")
+        {
+            let _ = remove_dir(entry.path());
+        }
+    }
+
+    Ok(Some(parent))
+}
+
+// a new cgroup for one build so each build gets the limits to itself
+fn delegated_cgroup(config: &Config) -> Result<Option<PathBuf>> {
+    let parent = match &*DELEGATED_CGROUP {
+        Ok(Some(parent)) => parent,
+        Ok(None) => return Ok(None),
+        Err(e) => bail!("{}", e),
+    };
+
+    let n = BUILD_CGROUPS.fetch_add(1, Ordering::Relaxed);
+    let dir = parent.join(format!("paru-build-{}-{}", std::process::id(), n));
+    create_dir_all(&dir).with_context(|| tr!("failed to create {}", dir.display()))?;
+
+    if let Some(max) = config.build_memory_max {
+        let mem = dir.join("memory.max");
+        write(&mem, max.cgroup_value()?)
+            .with_context(|| tr!("failed to write to {}", mem.display()))?;
+    }
+    if let Some(quota) = config.build_cpu_quota {
+        let cpu = dir.join("cpu.max");
+        write(&cpu, quota.cgroup_value())
+            .with_context(|| tr!("failed to write to {}", cpu.display()))?;
+    }
+
+    Ok(Some(dir))
+}
+
+/// Wraps a build command so it runs with the configured resource limits.
+/// Memory and CPU limits use a transient systemd scope, or a delegated cgroup
+/// when systemd-run can not start one, so they apply to everything the build spawns.
+pub fn limit_build(config: &Config, cmd: Command) -> Result<Command> {
+    let mut cmd = match config.build_nice {
+        Some(nice) => {
+            let mut prefix = Command::new("nice");
+            prefix.arg("-n").arg(nice.to_string());
+            wrap(prefix, &cmd)
+        }
+        None => cmd,
+    };
+
+    if config.build_memory_max.is_none() && config.build_cpu_quota.is_none() {
+        return Ok(cmd);
+    }
+
+    if *SYSTEMD_RUN {
+        return Ok(wrap(systemd_run(config), &cmd));
+    }
+
+    let Some(dir) = delegated_cgroup(config)? else {
+        bail!(tr!(
+            "BuildMemoryMax and BuildCPUQuota need systemd-run or a delegated cgroup"
+        ));
+    };
+
+    let path = dir.join("cgroup.procs");
+    let procs = File::options()
+        .write(true)
+        .open(&path)
+        .with_context(|| tr!("failed to open {}", path.display()))?;
+    // writing 0 moves the writing process, so the build starts inside the
+    // cgroup before it can spawn anything
+    unsafe {
+        cmd.pre_exec(move || (&procs).write_all(b"0"));
+    }
+    Ok(cmd)
+}
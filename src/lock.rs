@@ -0,0 +1,305 @@
+use crate::config::Config;
+use crate::devel::{git_source, load_devel_info, Vcs};
+use crate::util::{load_toml, pkg_base_or_name, save_toml};
+use crate::{exec, printtr, repo};
+
+use std::cmp::Ordering;
+use std::path::Path;
+use std::process::Command;
+
+use alpm::vercmp;
+use alpm_utils::DbListExt;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use srcinfo::Srcinfo;
+use tr::tr;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockSource {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub commit: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LockBase {
+    pub pkgbase: String,
+    // None for AUR packages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    pub pkgs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<LockSource>,
+}
+
+/// The AUR and PKGBUILD repo packages installed on a system and the commits
+/// they were built from, used with --locked to build the same thing elsewhere.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Lockfile {
+    #[serde(default, rename = "base")]
+    pub bases: Vec<LockBase>,
+}
+
+impl Lockfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        load_toml(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        save_toml(path, self)
+    }
+
+    /// Every package in the lockfile as a target.
+    pub fn targets(&self, config: &Config) -> Vec<String> {
+        self.bases
+            .iter()
+            .flat_map(|b| {
+                let repo = b.repo.as_deref().unwrap_or(config.aur_namespace());
+                b.pkgs.iter().map(move |p| format!("{}/{}", repo, p))
+            })
+            .collect()
+    }
+
+    pub fn base(&self, pkgbase: &str, repo: Option<&str>) -> Result<&LockBase> {
+        self.bases
+            .iter()
+            .find(|b| b.pkgbase == pkgbase && b.repo.as_deref() == repo)
+            .with_context(|| tr!("'{}' is not in the lockfile", pkgbase))
+    }
+
+    // PKGBUILD repos have to be checked out before their srcinfos are read
+    pub fn checkout_repos(&self, config: &Config) -> Result<()> {
+        let mut done = Vec::new();
+
+        for base in &self.bases {
+            let (Some(name), Some(commit)) = (&base.repo, &base.commit) else {
+                continue;
+            };
+            if done.contains(&name) {
+                continue;
+            }
+            let repo = config
+                .pkgbuild_repos
+                .repo(name)
+                .with_context(|| tr!("unknown pkgbuild repo: {}", name))?;
+            if repo.source.url().is_none() {
+                continue;
+            }
+
+            checkout(config, &repo.path, commit, name)?;
+            // srcinfos generated for the old checkout are stale now
+            exec::command(git(config, &repo.path).args([
+                "clean",
+                "-fqx",
+                "--",
+                ".SRCINFO",
+                "*/.SRCINFO",
+            ]))?;
+            done.push(name);
+        }
+
+        Ok(())
+    }
+}
+
+// the clones and mirrors belong to the build user, if there is one
+fn git(config: &Config, dir: &Path) -> Command {
+    let mut cmd = exec::build_user_command(config, &config.git_bin);
+    cmd.args(&config.git_flags).current_dir(dir);
+    cmd
+}
+
+fn has_commit(config: &Config, dir: &Path, commit: &str) -> Result<bool> {
+    let mut cmd = git(config, dir);
+    cmd.args(["cat-file", "-e", &format!("{}^{{commit}}", commit)]);
+    Ok(exec::command_output(&mut cmd)?.status.success())
+}
+
+fn head(config: &Config, dir: &Path) -> Result<String> {
+    let mut cmd = git(config, dir);
+    cmd.args(["rev-parse", "HEAD"]);
+    let output = exec::command_output(&mut cmd)?;
+    if !output.status.success() {
+        bail!(tr!("failed to read revision: {}", dir.display()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Resets a clone to the locked commit, fetching first if it is not known yet.
+pub fn checkout(config: &Config, dir: &Path, commit: &str, name: &str) -> Result<()> {
+    if !has_commit(config, dir, commit)? {
+        exec::command(git(config, dir).arg("fetch"))?;
+        if !has_commit(config, dir, commit)? {
+            bail!(tr!(
+                "commit {} of '{}' no longer exists upstream",
+                commit,
+                name
+            ));
+        }
+    }
+
+    exec::command(git(config, dir).args(["reset", "--hard", "-q", commit]))
+}
+
+/// Points the branches makepkg extracts the git sources from at their locked
+/// commits, so extracting again checks those out before prepare() runs. The
+/// mirrors in SRCDEST are found through the clones already extracted to src/
+/// and are reset by the next fetch.
+pub fn lock_sources(config: &Config, lock: &LockBase, srcinfo: &Srcinfo, dir: &Path) -> Result<()> {
+    let arch = config.alpm.architectures().first().unwrap_or_default();
+
+    for url in srcinfo.base.source.arch(arch) {
+        let Some((remote, name)) = git_source(url) else {
+            continue;
+        };
+        let Some(source) = lock.sources.iter().find(|s| s.url == remote) else {
+            continue;
+        };
+
+        let checkout = dir.join("src").join(name);
+        let mirror = git_output(config, &checkout, &["remote", "get-url", "origin"])?;
+        let mirror = Path::new(&mirror);
+        if !has_commit(config, mirror, &source.commit)? {
+            bail!(tr!(
+                "commit {} of '{}' no longer exists upstream",
+                source.commit,
+                source.url
+            ));
+        }
+
+        let branch = match &source.branch {
+            Some(branch) => format!("refs/heads/{}", branch),
+            None => git_output(config, mirror, &["symbolic-ref", "HEAD"])?,
+        };
+        exec::command(git(config, mirror).args(["update-ref", &branch, &source.commit]))?;
+    }
+
+    Ok(())
+}
+
+fn git_output(config: &Config, dir: &Path, args: &[&str]) -> Result<String> {
+    let mut cmd = git(config, dir);
+    cmd.args(args);
+    let output = exec::command_output(&mut cmd)?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// the newest commit of the clone that builds the installed version. Devel packages
+// bump their version when built so HEAD is used for them unless it is newer.
+fn installed_commit(config: &Config, dir: &Path, version: &str) -> Result<Option<String>> {
+    let commits = git_output(config, dir, &["rev-list", "HEAD", "--", ".SRCINFO"])?;
+
+    for (i, commit) in commits.lines().enumerate() {
+        let mut cmd = git(config, dir);
+        cmd.args(["show", &format!("{}:.SRCINFO", commit)]);
+        let Ok(output) = exec::command_output(&mut cmd) else {
+            continue;
+        };
+        let Ok(srcinfo) = Srcinfo::from_buf(output.stdout.as_slice()) else {
+            continue;
+        };
+        if srcinfo.version() == version {
+            // later commits that didn't touch .SRCINFO build the same version
+            return match i {
+                0 => head(config, dir).map(Some),
+                _ => Ok(Some(commit.to_string())),
+            };
+        }
+    }
+
+    let srcinfo = Srcinfo::from_path(dir.join(".SRCINFO"))
+        .with_context(|| tr!("failed to parse srcinfo for '{}'", dir.display()))?;
+    if vercmp(srcinfo.version().as_str(), version) == Ordering::Greater {
+        return Ok(None);
+    }
+    head(config, dir).map(Some)
+}
+
+pub fn write_lock(config: &Config, path: &Path) -> Result<i32> {
+    let devel_info = load_devel_info(config)?.unwrap_or_default();
+    let (_, aur_dbs) = repo::repo_aur_dbs(config);
+    let sync = config.alpm.syncdbs();
+    let mut lock = Lockfile::default();
+
+    for pkg in config.alpm.localdb().pkgs() {
+        let name = pkg.name();
+        if sync.pkg(name).is_ok() && !aur_dbs.iter().any(|db| db.pkg(name).is_ok()) {
+            continue;
+        }
+
+        let pkgbase = pkg_base_or_name(pkg);
+        if let Some(base) = lock.bases.iter_mut().find(|b| b.pkgbase == pkgbase) {
+            base.pkgs.push(name.to_string());
+            continue;
+        }
+
+        let (repo, commit) = match config.pkgbuild_repos.pkg(config, name) {
+            Some((repo_pkg, _)) => (
+                Some(repo_pkg.repo.clone()),
+                head(config, &repo_pkg.path).ok(),
+            ),
+            None => {
+                let dir = config.build_dir.join(pkgbase);
+                if !dir.join(".git").exists() {
+                    eprintln!(
+                        "{} {}",
+                        config.color.warning.paint("::"),
+                        tr!("no clone for '{}', not locking it", pkgbase)
+                    );
+                    continue;
+                }
+                let Some(commit) = installed_commit(config, &dir, pkg.version().as_str())? else {
+                    eprintln!(
+                        "{} {}",
+                        config.color.warning.paint("::"),
+                        tr!(
+                            "the clone of '{}' is past the installed version, not locking it",
+                            pkgbase
+                        )
+                    );
+                    continue;
+                };
+                (None, Some(commit))
+            }
+        };
+
+        let sources = devel_info
+            .info
+            .get(pkgbase)
+            .into_iter()
+            .flat_map(|info| &info.repos)
+            .filter(|r| r.vcs == Vcs::Git)
+            .map(|r| LockSource {
+                url: r.url.clone(),
+                branch: r.branch.clone(),
+                commit: r.commit.clone(),
+            })
+            .collect();
+
+        lock.bases.push(LockBase {
+            pkgbase: pkgbase.to_string(),
+            repo,
+            version: pkg.version().to_string(),
+            commit,
+            pkgs: vec![name.to_string()],
+            sources,
+        });
+    }
+
+    lock.bases.sort_by(|a, b| a.pkgbase.cmp(&b.pkgbase));
+    for base in &mut lock.bases {
+        base.sources.sort_by(|a, b| a.url.cmp(&b.url));
+    }
+    lock.save(path)?;
+
+    printtr!(
+        "locked {} package bases in {}",
+        lock.bases.len(),
+        path.display()
+    );
+    Ok(0)
+}
@@ -0,0 +1,247 @@
+use crate::config::{Config, Op};
+use crate::fmt::print_indent;
+use crate::install::{self, set_install_reason};
+use crate::util::{ask, load_toml, redirect_to_stderr, reopen_stdout};
+use crate::{exec, printtr};
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use alpm::{Db, Depend, PackageReason};
+use alpm_utils::depends::satisfies_dep;
+use ansiterm::Style;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tr::tr;
+
+/// The packages that should be explicitly installed on the system.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Manifest {
+    repo: Vec<String>,
+    groups: Vec<String>,
+    aur: Vec<String>,
+    pkgbuilds: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+struct Wanted {
+    name: String,
+    // the package with an optional version requirement, e.g. foo>=1.0
+    dep: String,
+    // the dep with the repo it has to come from, e.g. aur/foo>=1.0
+    target: String,
+    // the members of a group, which is satisfied by any of them being installed
+    members: Option<Vec<String>>,
+}
+
+impl Wanted {
+    fn new(dep: &str, repo: Option<&str>) -> Self {
+        let target = match repo {
+            Some(repo) => format!("{}/{}", repo, dep),
+            None => dep.to_string(),
+        };
+        Wanted {
+            name: Depend::new(dep).name().to_string(),
+            dep: dep.to_string(),
+            target,
+            members: None,
+        }
+    }
+
+    fn group(name: &str, members: Vec<String>) -> Self {
+        Wanted {
+            name: name.to_string(),
+            dep: name.to_string(),
+            target: name.to_string(),
+            members: Some(members),
+        }
+    }
+
+    // the installed packages that satisfy this entry, packages that are too old
+    // don't count and a package of the same name has to satisfy it itself
+    fn local_names(&self, db: &Db) -> Vec<String> {
+        if let Some(members) = &self.members {
+            return members
+                .iter()
+                .filter(|m| db.pkg(m.as_str()).is_ok())
+                .cloned()
+                .collect();
+        }
+
+        let dep = Depend::new(self.dep.as_str());
+        let pkg = match db.pkg(self.name.as_str()) {
+            Ok(pkg) => Some(pkg).filter(|p| satisfies_dep(&dep, p.name(), p.version())),
+            Err(_) => db.pkgs().find_satisfier(self.dep.as_str()),
+        };
+        pkg.map(|p| p.name().to_string()).into_iter().collect()
+    }
+
+    fn installed(&self, db: &Db) -> bool {
+        !self.local_names(db).is_empty()
+    }
+}
+
+fn wanted(config: &Config, manifest: &Manifest) -> Result<Vec<Wanted>> {
+    let mut wanted = Vec::new();
+
+    wanted.extend(manifest.repo.iter().map(|p| Wanted::new(p, None)));
+
+    for group in &manifest.groups {
+        let pkgs = config
+            .alpm
+            .syncdbs()
+            .iter()
+            .filter_map(|db| db.group(group.as_str()).ok())
+            .flat_map(|g| g.packages())
+            .collect::<Vec<_>>();
+        if pkgs.is_empty() {
+            bail!(tr!("unknown group: {}", group));
+        }
+        let members = pkgs.iter().map(|p| p.name().to_string()).collect();
+        wanted.push(Wanted::group(group, members));
+    }
+
+    wanted.extend(
+        manifest
+            .aur
+            .iter()
+            .map(|p| Wanted::new(p, Some(config.aur_namespace()))),
+    );
+
+    for (repo, pkgs) in &manifest.pkgbuilds {
+        if config.pkgbuild_repos.repo(repo).is_none() {
+            bail!(tr!("unknown pkgbuild repo: {}", repo));
+        }
+        wanted.extend(pkgs.iter().map(|p| Wanted::new(p, Some(repo))));
+    }
+
+    Ok(wanted)
+}
+
+fn print_pkgs(config: &Config, title: String, pkgs: &[String]) {
+    let c = config.color;
+    println!("{} {}", c.action.paint("::"), c.bold.paint(title));
+    print!("    ");
+    print_indent(Style::new(), 4, 4, config.cols, "  ", pkgs);
+}
+
+// the resolver may settle for a package that doesn't satisfy the version
+fn check_installed(config: &Config, wanted: &[Wanted]) -> Result<()> {
+    let alpm = config.new_alpm()?;
+    let db = alpm.localdb();
+    let mismatched = wanted
+        .iter()
+        .filter(|w| !w.installed(db))
+        .map(|w| match db.pkg(w.name.as_str()) {
+            Ok(pkg) => format!("{} ({})", w.dep, pkg.version()),
+            Err(_) => w.dep.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    if !mismatched.is_empty() {
+        bail!(tr!(
+            "installed packages do not match the manifest: {}",
+            mismatched.join(" ")
+        ));
+    }
+    Ok(())
+}
+
+pub async fn apply(config: &mut Config, path: &Path) -> Result<i32> {
+    let manifest: Manifest = load_toml(path)?;
+    let wanted = wanted(config, &manifest)?;
+    let dry_run = config.print_plan.is_some();
+
+    let db = config.alpm.localdb();
+    let names = wanted
+        .iter()
+        .flat_map(|w| w.local_names(db))
+        .collect::<HashSet<_>>();
+
+    let missing = wanted
+        .iter()
+        .filter(|w| !w.installed(db))
+        .map(|w| w.target.clone())
+        .collect::<Vec<_>>();
+
+    // group members are left alone so deselected ones stay that way
+    let explicit = wanted
+        .iter()
+        .filter(|w| w.members.is_none())
+        .flat_map(|w| w.local_names(db))
+        .filter(|name| {
+            db.pkg(name.as_str())
+                .is_ok_and(|p| p.reason() == PackageReason::Depend)
+        })
+        .collect::<Vec<_>>();
+
+    let mut unlisted = db
+        .pkgs()
+        .iter()
+        .filter(|p| p.reason() == PackageReason::Explicit && !names.contains(p.name()))
+        .map(|p| p.name().to_string())
+        .collect::<Vec<_>>();
+    unlisted.sort();
+
+    // in a dry run stdout is kept for the plan
+    let stdout = if dry_run {
+        Some(redirect_to_stderr()?)
+    } else {
+        None
+    };
+
+    if !explicit.is_empty() {
+        print_pkgs(
+            config,
+            tr!("Packages to mark as explicitly installed:"),
+            &explicit,
+        );
+    }
+    if !unlisted.is_empty() {
+        print_pkgs(
+            config,
+            tr!("Explicitly installed packages not in the manifest:"),
+            &unlisted,
+        );
+    }
+
+    config.set_op_args_globals(Op::Sync);
+
+    if let Some(stdout) = stdout {
+        if missing.is_empty() {
+            printtr!(" the system already matches the manifest");
+        }
+        reopen_stdout(&stdout)?;
+        if !missing.is_empty() {
+            install::install(config, &missing).await?;
+        }
+        return Ok(0);
+    }
+
+    if !missing.is_empty() {
+        install::install(config, &missing).await?;
+        check_installed(config, &wanted)?;
+    }
+
+    set_install_reason(config, "asexplicit", &explicit)?;
+
+    if !unlisted.is_empty()
+        && ask(
+            config,
+            &tr!("Remove the packages not in the manifest?"),
+            false,
+        )
+    {
+        let mut args = config.pacman_globals();
+        args.op("remove").arg("recursive");
+        args.targets = unlisted.iter().map(|s| s.as_str()).collect();
+        return Ok(exec::pacman(config, &args)?.code());
+    }
+
+    if missing.is_empty() && explicit.is_empty() {
+        printtr!(" there is nothing to do");
+    }
+
+    Ok(0)
+}
@@ -94,9 +94,24 @@ impl Raur for Mock {
 
     async fn search_by<S: AsRef<str> + Send + Sync>(
         &self,
-        _pkg: S,
-        _by: SearchBy,
+        query: S,
+        by: SearchBy,
     ) -> StdResult<Vec<Package>, Error> {
-        unimplemented!()
+        let query = query.as_ref();
+        let ret = self
+            .pkgs
+            .values()
+            .filter(|pkg| match by {
+                SearchBy::Name | SearchBy::NameDesc => pkg.name.contains(query),
+                SearchBy::Maintainer => pkg.maintainer.as_deref() == Some(query),
+                SearchBy::Depends => pkg.depends.iter().any(|d| d == query),
+                SearchBy::MakeDepends => pkg.make_depends.iter().any(|d| d == query),
+                SearchBy::CheckDepends => pkg.check_depends.iter().any(|d| d == query),
+                _ => false,
+            })
+            .cloned()
+            .collect();
+
+        Ok(ret)
     }
 }
@@ -0,0 +1,94 @@
+use crate::config::Config;
+use crate::util::{input, NumberMenu};
+
+use anyhow::Result;
+use tr::tr;
+
+#[derive(Debug)]
+struct OptDep {
+    name: String,
+    desc: Option<String>,
+    // the installed targets that want it
+    wanted_by: Vec<String>,
+}
+
+fn missing_optdepends(config: &Config, targets: &[String]) -> Result<Vec<OptDep>> {
+    // the transaction changed the local db so it has to be read again
+    let alpm = config.new_alpm()?;
+    let db = alpm.localdb();
+    let mut optdeps: Vec<OptDep> = Vec::new();
+
+    for pkg in targets.iter().filter_map(|t| db.pkg(t.as_str()).ok()) {
+        for dep in pkg.optdepends() {
+            if db.pkgs().find_satisfier(dep.name()).is_some() {
+                continue;
+            }
+
+            match optdeps.iter_mut().find(|o| o.name == dep.name()) {
+                Some(o) => o.wanted_by.push(pkg.name().to_string()),
+                None => optdeps.push(OptDep {
+                    name: dep.name().to_string(),
+                    desc: dep.desc().map(|d| d.to_string()),
+                    wanted_by: vec![pkg.name().to_string()],
+                }),
+            }
+        }
+    }
+
+    Ok(optdeps)
+}
+
+/// Lists the optional dependencies of the installed targets that are not
+/// installed yet and returns the ones the user picks.
+pub fn pick(config: &Config, targets: &[String]) -> Result<Vec<String>> {
+    if config.no_confirm || targets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let optdeps = missing_optdepends(config, targets)?;
+    if optdeps.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let c = config.color;
+    let pad = optdeps.len().to_string().len();
+
+    println!();
+    println!(
+        "{} {}",
+        c.action.paint("::"),
+        c.bold.paint(tr!(
+            "There are {} optional dependencies available:",
+            optdeps.len()
+        )),
+    );
+    for (n, opt) in optdeps.iter().enumerate() {
+        let n = format!("{:>pad$}", n + 1, pad = pad);
+        println!(
+            "    {} {} {}",
+            c.number_menu.paint(n),
+            c.bold.paint(&opt.name),
+            tr!("(for {})", opt.wanted_by.join(", ")),
+        );
+        if let Some(desc) = &opt.desc {
+            println!("    {:pad$} {}", "", desc, pad = pad);
+        }
+    }
+
+    let input = input(
+        config,
+        &tr!("Optional dependencies to install (eg: 1 2 3, 1-3) (default=none):"),
+    );
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let menu = NumberMenu::new(input.trim());
+    let picked = optdeps
+        .into_iter()
+        .enumerate()
+        .filter(|(n, opt)| menu.contains(n + 1, &opt.name))
+        .map(|(_, opt)| opt.name)
+        .collect();
+    Ok(picked)
+}
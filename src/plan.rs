@@ -0,0 +1,211 @@
+use crate::config::{Config, PlanFormat};
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use aur_depends::{Actions, Base, Conflict};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Plan<'a> {
+    install: Vec<RepoInstall<'a>>,
+    build: Vec<BuildBase<'a>>,
+    conflicts: Vec<PlanConflict<'a>>,
+    inner_conflicts: Vec<PlanConflict<'a>>,
+    missing: Vec<Missing<'a>>,
+    unneeded: Vec<Unneeded<'a>>,
+}
+
+#[derive(Serialize)]
+struct RepoInstall<'a> {
+    name: &'a str,
+    repo: Option<&'a str>,
+    version: String,
+    local_version: Option<String>,
+    make: bool,
+    target: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Source {
+    Aur,
+    Pkgbuild,
+}
+
+#[derive(Serialize)]
+struct BuildBase<'a> {
+    source: Source,
+    repo: Option<&'a str>,
+    pkgbase: &'a str,
+    version: String,
+    devel: bool,
+    build: bool,
+    pkgs: Vec<BuildPkg<'a>>,
+}
+
+#[derive(Serialize)]
+struct BuildPkg<'a> {
+    name: &'a str,
+    local_version: Option<String>,
+    make: bool,
+    target: bool,
+}
+
+#[derive(Serialize)]
+struct PlanConflict<'a> {
+    pkg: &'a str,
+    conflicting: Vec<Conflicting<'a>>,
+}
+
+#[derive(Serialize)]
+struct Conflicting<'a> {
+    pkg: &'a str,
+    conflict: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Missing<'a> {
+    dep: &'a str,
+    stack: Vec<Wanted<'a>>,
+}
+
+#[derive(Serialize)]
+struct Wanted<'a> {
+    pkg: &'a str,
+    dep: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Unneeded<'a> {
+    name: &'a str,
+    version: &'a str,
+}
+
+fn local_version(config: &Config, pkg: &str) -> Option<String> {
+    let pkg = config.alpm.localdb().pkg(pkg).ok()?;
+    Some(pkg.version().to_string())
+}
+
+fn conflicts(conflicts: &[Conflict]) -> Vec<PlanConflict<'_>> {
+    conflicts
+        .iter()
+        .map(|c| PlanConflict {
+            pkg: &c.pkg,
+            conflicting: c
+                .conflicting
+                .iter()
+                .map(|c| Conflicting {
+                    pkg: &c.pkg,
+                    conflict: c.conflict.as_deref(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn build_base<'a>(config: &Config, base: &'a Base, devel: &HashSet<String>) -> BuildBase<'a> {
+    let (source, repo, pkgs) = match base {
+        Base::Aur(a) => {
+            let pkgs = a
+                .pkgs
+                .iter()
+                .map(|p| BuildPkg {
+                    name: &p.pkg.name,
+                    local_version: local_version(config, &p.pkg.name),
+                    make: p.make,
+                    target: p.target,
+                })
+                .collect();
+            (Source::Aur, None, pkgs)
+        }
+        Base::Pkgbuild(c) => {
+            let pkgs = c
+                .pkgs
+                .iter()
+                .map(|p| BuildPkg {
+                    name: &p.pkg.pkgname,
+                    local_version: local_version(config, &p.pkg.pkgname),
+                    make: p.make,
+                    target: p.target,
+                })
+                .collect();
+            (Source::Pkgbuild, Some(c.repo.as_str()), pkgs)
+        }
+    };
+
+    BuildBase {
+        source,
+        repo,
+        pkgbase: base.package_base(),
+        version: base.version(),
+        devel: base.packages().any(|p| devel.contains(p)),
+        build: base.build(),
+        pkgs,
+    }
+}
+
+pub fn print_plan(
+    config: &Config,
+    format: PlanFormat,
+    actions: &Actions,
+    devel: &HashSet<String>,
+) -> Result<()> {
+    let local = actions.calculate_conflicts(!config.chroot);
+    let inner = actions.calculate_inner_conflicts(!config.chroot);
+
+    let install = actions
+        .install
+        .iter()
+        .map(|p| RepoInstall {
+            name: p.pkg.name(),
+            repo: p.pkg.db().map(|db| db.name()),
+            version: p.pkg.version().to_string(),
+            local_version: local_version(config, p.pkg.name()),
+            make: p.make,
+            target: p.target,
+        })
+        .collect();
+
+    let missing = actions
+        .missing
+        .iter()
+        .map(|m| Missing {
+            dep: &m.dep,
+            stack: m
+                .stack
+                .iter()
+                .map(|s| Wanted {
+                    pkg: &s.pkg,
+                    dep: s.dep.as_deref(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let plan = Plan {
+        install,
+        build: actions
+            .build
+            .iter()
+            .map(|b| build_base(config, b, devel))
+            .collect(),
+        conflicts: conflicts(&local),
+        inner_conflicts: conflicts(&inner),
+        missing,
+        unneeded: actions
+            .unneeded
+            .iter()
+            .map(|u| Unneeded {
+                name: &u.name,
+                version: &u.version,
+            })
+            .collect(),
+    };
+
+    match format {
+        PlanFormat::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+    }
+
+    Ok(())
+}
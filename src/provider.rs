@@ -0,0 +1,149 @@
+use crate::config::{Colors, Config};
+use crate::print_error;
+use crate::util::{load_state, save_state};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use alpm::Depend;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+/// The provider picked for each dependency the last time paru asked.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(transparent)]
+struct Saved {
+    deps: BTreeMap<String, String>,
+}
+
+impl Saved {
+    fn load(state_dir: &Path) -> Result<Self> {
+        let saved = load_state(state_dir, "providers.toml")?;
+        Ok(saved.unwrap_or_default())
+    }
+
+    fn save(&self, state_dir: &Path) -> Result<()> {
+        save_state(state_dir, "providers.toml", self)
+    }
+}
+
+/// Picks providers from the config and the saved choices, and saves the
+/// choices the user makes when asked.
+#[derive(Debug)]
+pub struct Providers {
+    configured: BTreeMap<String, String>,
+    // only read once a provider is needed, None when it could not be read
+    saved: Option<Option<Saved>>,
+    state_dir: PathBuf,
+    no_confirm: bool,
+    color: Colors,
+}
+
+impl Providers {
+    pub fn new(config: &Config) -> Self {
+        Providers {
+            configured: config.providers.clone(),
+            saved: None,
+            state_dir: config.state_dir.clone(),
+            no_confirm: config.no_confirm,
+            color: config.color,
+        }
+    }
+
+    fn saved(&mut self) -> Option<&mut Saved> {
+        let (state_dir, color) = (&self.state_dir, self.color);
+        self.saved
+            .get_or_insert_with(|| match Saved::load(state_dir) {
+                Ok(saved) => Some(saved),
+                Err(e) => {
+                    // a broken file only costs the saved choices
+                    eprintln!("{} {:#}", color.warning.paint(tr!("warning:")), e);
+                    None
+                }
+            })
+            .as_mut()
+    }
+
+    /// The index of the provider to use for dep. ask is only called when
+    /// there is no usable choice for dep yet.
+    pub fn choose(&mut self, dep: &str, pkgs: &[&str], ask: impl FnOnce() -> usize) -> usize {
+        let name = Depend::new(dep).name().to_string();
+        let known = match self.configured.get(&name) {
+            Some(pkg) => Some(pkg.clone()),
+            None => self.saved().and_then(|s| s.deps.get(&name).cloned()),
+        };
+
+        if let Some(index) = known.and_then(|k| pkgs.iter().position(|p| *p == k)) {
+            println!(
+                "{} {}",
+                self.color.action.paint("::"),
+                self.color.bold.paint(tr!(
+                    "Using {pkg} to provide {dep}",
+                    pkg = pkgs[index],
+                    dep = dep
+                ))
+            );
+            return index;
+        }
+
+        let index = ask();
+        // the default is picked without asking under --noconfirm
+        if self.no_confirm {
+            return index;
+        }
+
+        let state_dir = self.state_dir.clone();
+        let color = self.color;
+        // a file that could not be read is left alone
+        if let Some(saved) = self.saved() {
+            saved.deps.insert(name, pkgs[index].to_string());
+            if let Err(e) = saved.save(&state_dir) {
+                print_error(color.error, e);
+            }
+        }
+        index
+    }
+}
+
+pub fn list(config: &Config) -> Result<i32> {
+    let c = config.color;
+    let saved = Saved::load(&config.state_dir)?;
+
+    for (dep, pkg) in &config.providers {
+        println!("{} {} {}", c.bold.paint(dep), pkg, tr!("(config)"));
+    }
+    for (dep, pkg) in &saved.deps {
+        if !config.providers.contains_key(dep) {
+            println!("{} {}", c.bold.paint(dep), pkg);
+        }
+    }
+
+    Ok(0)
+}
+
+/// Forgets the saved providers of the targets, or all of them when there
+/// are no targets.
+pub fn reset(config: &Config) -> Result<i32> {
+    let mut saved = Saved::load(&config.state_dir)?;
+    let len = saved.deps.len();
+    let mut ret = 0;
+
+    if config.targets.is_empty() {
+        saved.deps.clear();
+    }
+    for target in &config.targets {
+        if saved.deps.remove(target).is_none() {
+            print_error(
+                config.color.error,
+                anyhow!(tr!("no saved provider for {}", target)),
+            );
+            ret = 1;
+        }
+    }
+
+    if saved.deps.len() != len {
+        saved.save(&config.state_dir)?;
+    }
+    Ok(ret)
+}
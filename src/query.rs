@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use crate::config::{Config, Mode};
 use crate::devel::{filter_devel_updates, possible_devel_updates};
 use crate::exec;
+use crate::upstream::{print_release, upstream_releases};
 use crate::util::split_repo_aur_pkgs;
 
 use anyhow::Result;
@@ -91,7 +92,7 @@ pub async fn print_upgrade_list(config: &mut Config) -> Result<i32> {
         if config.mode.aur() {
             async fn devel_up(config: &Config) -> Result<Vec<String>> {
                 if config.devel {
-                    let updates = possible_devel_updates(config).await?;
+                    let updates = possible_devel_updates(config, false).await?;
                     Ok(updates)
                 } else {
                     Ok(Vec::new())
@@ -106,22 +107,29 @@ pub async fn print_upgrade_list(config: &mut Config) -> Result<i32> {
             let (_, devel) = try_join!(aur_up(config, &mut cache, &aur), devel_up(config))?;
             let devel = filter_devel_updates(config, &mut cache, &devel).await?;
 
-            for target in aur {
+            for &target in &aur {
                 let local_pkg = db.pkg(target).unwrap();
-                if let Some(pkg) = cache.get(target) {
-                    let devel = devel.iter().any(|d| d.pkg == pkg.name);
+                let devel = devel.iter().any(|d| d.pkg == target);
 
-                    if alpm::Version::new(&*pkg.version) > local_pkg.version() || devel {
-                        aur_ret = 0;
-
-                        let version = if devel {
-                            "latest-commit"
-                        } else {
-                            pkg.version.as_str()
-                        };
+                let version = if devel {
+                    Some("latest-commit")
+                } else {
+                    cache
+                        .get(target)
+                        .filter(|pkg| alpm::Version::new(&*pkg.version) > local_pkg.version())
+                        .map(|pkg| pkg.version.as_str())
+                };
+
+                if let Some(version) = version {
+                    aur_ret = 0;
+                    print_upgrade(config, target, local_pkg.version().as_str(), version);
+                }
+            }
 
-                        print_upgrade(config, &pkg.name, local_pkg.version().as_str(), version);
-                    }
+            let releases = upstream_releases(config, &cache, &aur).await?;
+            if !config.args.has_arg("q", "quiet") {
+                for release in &releases {
+                    print_release(config, release);
                 }
             }
         }
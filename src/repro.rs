@@ -0,0 +1,97 @@
+use crate::exec;
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use tr::tr;
+
+// the attributes compared for each file in the package
+const MTREE_OPTIONS: &str = "!all,type,mode,uid,gid,time,size,sha256,link";
+
+// lists the files in a package along with their attributes using bsdtar's mtree output
+fn mtree(pkg: &Path) -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+    let mut at = OsString::from("@");
+    at.push(pkg);
+
+    let mut cmd = Command::new("bsdtar");
+    cmd.args(["--format=mtree", "--options", MTREE_OPTIONS, "-cf", "-"])
+        .arg(at);
+    let output = exec::command_output(&mut cmd)?;
+    if !output.status.success() {
+        bail!(
+            "{}: {}",
+            tr!("failed to read package: {}", pkg.display()),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let files = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.starts_with('/'))
+        .filter_map(|l| {
+            let mut words = l.split_whitespace();
+            let path = words.next()?.trim_start_matches("./").to_string();
+            let attrs = words
+                .filter_map(|w| w.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            Some((path, attrs))
+        })
+        .collect();
+    Ok(files)
+}
+
+fn sha256(path: &Path) -> Result<Vec<u8>> {
+    let file = File::open(path).with_context(|| tr!("failed to read file: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(file), &mut hasher)
+        .with_context(|| tr!("failed to read file: {}", path.display()))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Compares two builds of the same package file by file and returns the differences.
+pub fn compare(first: &Path, second: &Path) -> Result<Vec<String>> {
+    if !second.exists() {
+        return Ok(vec![tr!("package is missing from the second build")]);
+    }
+    if sha256(first)? == sha256(second)? {
+        return Ok(Vec::new());
+    }
+
+    let first = mtree(first)?;
+    let second = mtree(second)?;
+    let mut diffs = Vec::new();
+
+    for (path, attrs) in &first {
+        let Some(other) = second.get(path) else {
+            diffs.push(tr!("{}: only in the first build", path));
+            continue;
+        };
+
+        let differ = attrs
+            .iter()
+            .filter(|(k, v)| other.get(*k) != Some(*v))
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>();
+        if !differ.is_empty() {
+            diffs.push(tr!("{}: {} differs", path, differ.join(", ")));
+        }
+    }
+
+    for path in second.keys().filter(|p| !first.contains_key(*p)) {
+        diffs.push(tr!("{}: only in the second build", path));
+    }
+
+    // the archives differ but the files do not, the compression or file order does
+    if diffs.is_empty() {
+        diffs.push(tr!("package archives differ"));
+    }
+
+    Ok(diffs)
+}
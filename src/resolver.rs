@@ -1,8 +1,10 @@
 use crate::config::{Alpm, Config, LocalRepos, YesNoAll, YesNoAllTree};
 use crate::fmt::color_repo;
+use crate::provider::Providers;
 use crate::util::{get_provider, NumberMenu};
 use crate::RaurHandle;
 
+use std::cell::RefCell;
 use std::io::{stdin, stdout, BufRead, Write};
 
 use aur_depends::{Flags, PkgbuildRepo, Resolver};
@@ -60,6 +62,7 @@ pub fn flags(config: &mut Config) -> aur_depends::Flags {
 
 pub fn resolver<'a, 'b>(
     config: &Config,
+    providers: Providers,
     alpm: &'a Alpm,
     raur: &'b RaurHandle,
     cache: &'b mut Cache,
@@ -131,25 +134,28 @@ pub fn resolver<'a, 'b>(
         });
 
     if !config.args.has_arg("u", "sysupgrade") {
+        let providers = RefCell::new(providers);
         resolver = resolver.provider_callback(move |dep, pkgs| {
-            let prompt = tr!(
-                "There are {n} providers available for {pkg}:",
-                n = pkgs.len(),
-                pkg = dep
-            );
-            println!("{} {}", c.action.paint("::"), c.bold.paint(prompt));
-            println!(
-                "{} {} {}:",
-                c.action.paint("::"),
-                c.bold.paint(tr!("Repository")),
-                color_repo(c.enabled, "AUR")
-            );
-            print!("    ");
-            for (n, pkg) in pkgs.iter().enumerate() {
-                print!("{}) {}  ", n + 1, pkg);
-            }
+            providers.borrow_mut().choose(dep, pkgs, || {
+                let prompt = tr!(
+                    "There are {n} providers available for {pkg}:",
+                    n = pkgs.len(),
+                    pkg = dep
+                );
+                println!("{} {}", c.action.paint("::"), c.bold.paint(prompt));
+                println!(
+                    "{} {} {}:",
+                    c.action.paint("::"),
+                    c.bold.paint(tr!("Repository")),
+                    color_repo(c.enabled, "AUR")
+                );
+                print!("    ");
+                for (n, pkg) in pkgs.iter().enumerate() {
+                    print!("{}) {}  ", n + 1, pkg);
+                }
 
-            get_provider(pkgs.len(), no_confirm)
+                get_provider(pkgs.len(), no_confirm)
+            })
         });
     }
 
@@ -0,0 +1,99 @@
+use crate::config::{Config, Op};
+use crate::fmt::print_indent;
+use crate::install;
+use crate::util::{load_state, save_state};
+
+use std::fs::remove_file;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use ansiterm::Style;
+use anyhow::{Context, Result};
+use aur_depends::Base;
+use serde::{Deserialize, Serialize};
+use tr::tr;
+
+/// The bases that failed to build in the last transaction and the targets of
+/// that transaction, saved so they can be built again with --retry-failed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Failed {
+    pub bases: Vec<String>,
+    pub targets: Vec<String>,
+}
+
+impl Failed {
+    // the failed bases may only be dependencies, so the transaction's own
+    // targets are kept too to pull them back in
+    pub fn new(config: &Config, failed: &[Base], targets: &[String]) -> Self {
+        let mut targets = targets.to_vec();
+
+        for base in failed {
+            match base {
+                Base::Aur(base) => targets.extend(
+                    base.pkgs
+                        .iter()
+                        .filter(|p| p.target)
+                        .map(|p| format!("{}/{}", config.aur_namespace(), p.pkg.name)),
+                ),
+                Base::Pkgbuild(base) => targets.extend(
+                    base.pkgs
+                        .iter()
+                        .filter(|p| p.target)
+                        .map(|p| format!("{}/{}", base.repo, p.pkg.pkgname)),
+                ),
+            }
+        }
+
+        targets.sort_unstable();
+        targets.dedup();
+
+        Failed {
+            bases: failed
+                .iter()
+                .map(|b| b.package_base().to_string())
+                .collect(),
+            targets,
+        }
+    }
+
+    fn path(config: &Config) -> PathBuf {
+        config.state_dir.join("failed.toml")
+    }
+
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        load_state(&config.state_dir, "failed.toml")
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        save_state(&config.state_dir, "failed.toml", self)
+    }
+
+    pub fn remove(config: &Config) -> Result<()> {
+        let path = Self::path(config);
+        match remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| tr!("failed to remove: {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub async fn retry_failed(config: &mut Config) -> Result<i32> {
+    let failed = Failed::load(config)?.context(tr!("there are no failed builds to retry"))?;
+
+    let c = config.color;
+    println!(
+        "{} {}",
+        c.action.paint("::"),
+        c.bold.paint(tr!("Retrying failed builds..."))
+    );
+    print!("    ");
+    print_indent(Style::new(), 0, 4, config.cols, "  ", &failed.bases);
+
+    // the targets that did get installed don't need building again
+    config.set_op_args_globals(Op::Sync);
+    config.args.arg("needed");
+    install::install(config, &failed.targets).await?;
+    Ok(0)
+}
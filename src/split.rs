@@ -0,0 +1,324 @@
+use crate::config::Config;
+use crate::util::{input, load_state, save_state, NumberMenu};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use alpm_utils::Targ;
+use anyhow::{Context, Result};
+use raur::Raur;
+use serde::{Deserialize, Serialize};
+use srcinfo::Srcinfo;
+use tr::tr;
+
+// the repo picks of AUR bases are saved under, independent of aur_namespace
+const AUR: &str = "aur";
+
+/// The packages picked for each split pkgbase of each repo, used as the
+/// default the next time the base is a target or upgraded.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+struct Picks {
+    repos: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl Picks {
+    fn load(config: &Config) -> Result<Self> {
+        let picks = load_state(&config.state_dir, "splits.toml")?;
+        Ok(picks.unwrap_or_default())
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        save_state(&config.state_dir, "splits.toml", self)
+    }
+
+    fn get(&self, base: &SplitBase) -> Option<&Vec<String>> {
+        self.repos.get(base.key())?.get(&base.pkgbase)
+    }
+
+    fn insert(&mut self, base: &SplitBase, pick: Vec<String>) {
+        self.repos
+            .entry(base.key().to_string())
+            .or_default()
+            .insert(base.pkgbase.clone(), pick);
+    }
+}
+
+#[derive(Debug)]
+struct SplitBase {
+    repo: String,
+    aur: bool,
+    pkgbase: String,
+    // every package of the base and its description
+    pkgs: Vec<(String, Option<String>)>,
+    targets: Vec<String>,
+    upgrades: Vec<String>,
+}
+
+impl SplitBase {
+    fn new(repo: &str, aur: bool, srcinfo: &Srcinfo) -> Self {
+        SplitBase {
+            repo: repo.to_string(),
+            aur,
+            pkgbase: srcinfo.base.pkgbase.clone(),
+            pkgs: srcinfo
+                .pkgs
+                .iter()
+                .map(|p| (p.pkgname.clone(), p.pkgdesc.clone()))
+                .collect(),
+            targets: Vec::new(),
+            upgrades: Vec::new(),
+        }
+    }
+
+    fn key(&self) -> &str {
+        if self.aur {
+            AUR
+        } else {
+            &self.repo
+        }
+    }
+}
+
+/// The split packages to add to and drop from the targets.
+#[derive(Debug, Default)]
+pub struct Picked {
+    pub add: Vec<(String, String)>,
+    pub drop: HashSet<String>,
+}
+
+impl Picked {
+    pub fn targets(&self) -> impl Iterator<Item = Targ<'_>> {
+        self.add.iter().map(|(repo, pkg)| Targ {
+            repo: Some(repo),
+            pkg,
+        })
+    }
+}
+
+fn add_target(bases: &mut Vec<SplitBase>, base: SplitBase, target: &str, upgrade: bool) {
+    let i = match bases
+        .iter()
+        .position(|b| b.repo == base.repo && b.pkgbase == base.pkgbase)
+    {
+        Some(i) => i,
+        None => {
+            bases.push(base);
+            bases.len() - 1
+        }
+    };
+    if upgrade {
+        bases[i].upgrades.push(target.to_string());
+    } else {
+        bases[i].targets.push(target.to_string());
+    }
+}
+
+// the AUR can't list the packages of a base so they are read from the clones,
+// which are downloaded first unless they are up to date
+fn aur_srcinfos(config: &Config, pkgs: &[raur::Package]) -> Result<HashMap<String, Srcinfo>> {
+    let read = |pkgbase: &str| Srcinfo::from_path(config.build_dir.join(pkgbase).join(".SRCINFO"));
+
+    let mut outdated = Vec::new();
+    for pkg in pkgs {
+        let base = pkg.package_base.as_str();
+        if !outdated.contains(&base) && !read(base).is_ok_and(|s| s.version() == pkg.version) {
+            outdated.push(base);
+        }
+    }
+    // printing the plan must not change the clones
+    if !outdated.is_empty() && config.print_plan.is_none() {
+        config.fetch.download_cb(&outdated, |_| ())?;
+    }
+
+    let mut srcinfos = HashMap::new();
+    for pkg in pkgs {
+        if srcinfos.contains_key(&pkg.package_base) {
+            continue;
+        }
+        match read(&pkg.package_base) {
+            Ok(srcinfo) => {
+                srcinfos.insert(pkg.package_base.clone(), srcinfo);
+            }
+            Err(_) if config.print_plan.is_some() => (),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| tr!("failed to parse srcinfo for '{}'", pkg.package_base))
+            }
+        }
+    }
+    Ok(srcinfos)
+}
+
+async fn split_bases(
+    config: &Config,
+    targets: &[Targ<'_>],
+    upgrades: &[Targ<'_>],
+) -> Result<Vec<SplitBase>> {
+    let mut bases = Vec::new();
+    let mut aur = Vec::new();
+
+    let all = targets
+        .iter()
+        .map(|t| (t, false))
+        .chain(upgrades.iter().map(|t| (t, true)));
+    for (targ, upgrade) in all {
+        let repo_pkg = match targ.repo {
+            Some(repo) if repo == config.aur_namespace() => None,
+            Some(repo) => config
+                .pkgbuild_repos
+                .repo(repo)
+                .and_then(|r| r.pkg(config, targ.pkg))
+                .map(|(p, _)| p),
+            None => config.pkgbuild_repos.pkg(config, targ.pkg).map(|(p, _)| p),
+        };
+
+        match repo_pkg {
+            Some(p) => {
+                let base = SplitBase::new(&p.repo, false, &p.srcinfo);
+                add_target(&mut bases, base, targ.pkg, upgrade);
+            }
+            None if targ.repo.is_none_or(|r| r == config.aur_namespace()) => {
+                aur.push((targ.pkg, upgrade))
+            }
+            None => (),
+        }
+    }
+
+    if aur.is_empty() || !config.mode.aur() {
+        return Ok(bases);
+    }
+
+    let names = aur.iter().map(|(pkg, _)| *pkg).collect::<Vec<_>>();
+    let pkgs = config.raur.info(&names).await?;
+    let srcinfos = aur_srcinfos(config, &pkgs)?;
+
+    for pkg in &pkgs {
+        let Some(srcinfo) = srcinfos.get(&pkg.package_base) else {
+            continue;
+        };
+        let upgrade = aur.iter().any(|(p, u)| *p == pkg.name && *u);
+        let base = SplitBase::new(config.aur_namespace(), true, srcinfo);
+        add_target(&mut bases, base, &pkg.name, upgrade);
+    }
+
+    Ok(bases)
+}
+
+fn menu(config: &Config, base: &SplitBase, default: &[String]) -> Vec<String> {
+    let c = config.color;
+    let pad = base.pkgs.len().to_string().len();
+
+    println!(
+        "{} {} {}:",
+        c.action.paint("::"),
+        c.bold
+            .paint(tr!("There are {} packages in pkgbase", base.pkgs.len())),
+        c.bold.paint(&base.pkgbase),
+    );
+    for (n, (pkg, desc)) in base.pkgs.iter().enumerate() {
+        let n = format!("{:>pad$}", n + 1, pad = pad);
+        print!("    {} {}", c.number_menu.paint(n), c.bold.paint(pkg));
+        if config.alpm.localdb().pkg(pkg.as_str()).is_ok() {
+            print!(" {}", c.ss_installed.paint(tr!("[installed]")));
+        }
+        println!();
+        if let Some(desc) = desc {
+            println!("    {:pad$} {}", "", desc, pad = pad);
+        }
+    }
+
+    let default_nums = base
+        .pkgs
+        .iter()
+        .enumerate()
+        .filter(|(_, (pkg, _))| default.contains(pkg))
+        .map(|(n, _)| (n + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let input = input(
+        config,
+        &tr!(
+            "Packages to install (eg: 1 2 3, 1-3) (default={}):",
+            default_nums
+        ),
+    );
+
+    if input.trim().is_empty() {
+        return default.to_vec();
+    }
+
+    let menu = NumberMenu::new(input.trim());
+    base.pkgs
+        .iter()
+        .enumerate()
+        .filter(|(n, (pkg, _))| menu.contains(n + 1, pkg))
+        .map(|(_, (pkg, _))| pkg.clone())
+        .collect()
+}
+
+/// Lets the user pick which packages of each split target base to install.
+/// The last pick of a base is the default, and is installed along with
+/// upgrades of the base without asking.
+pub async fn pick(config: &Config, targets: &[Targ<'_>], upgrades: &[Targ<'_>]) -> Result<Picked> {
+    let mut picked = Picked::default();
+    if targets.is_empty() && upgrades.is_empty() {
+        return Ok(picked);
+    }
+
+    let bases = split_bases(config, targets, upgrades).await?;
+    let mut picks = Picks::load(config)?;
+    let mut changed = false;
+
+    for base in bases.iter().filter(|b| b.pkgs.len() > 1) {
+        if base.targets.is_empty() {
+            // packages picked before that are not installed any more are
+            // brought back, as long as they are still part of the base
+            let Some(pick) = picks.get(base) else {
+                continue;
+            };
+            let db = config.alpm.localdb();
+            picked.add.extend(
+                base.pkgs
+                    .iter()
+                    .map(|(p, _)| p)
+                    .filter(|p| pick.contains(p) && !base.upgrades.contains(p))
+                    .filter(|p| db.pkg(p.as_str()).is_err())
+                    .map(|p| (base.repo.clone(), p.clone())),
+            );
+            continue;
+        }
+
+        let default = match picks.get(base) {
+            Some(pick) => base
+                .pkgs
+                .iter()
+                .map(|(p, _)| p)
+                .filter(|p| pick.contains(p) || base.targets.contains(p))
+                .cloned()
+                .collect(),
+            None => base.targets.clone(),
+        };
+
+        let pick = menu(config, base, &default);
+
+        picked
+            .drop
+            .extend(base.targets.iter().filter(|t| !pick.contains(t)).cloned());
+        picked.add.extend(
+            pick.iter()
+                .filter(|p| !base.targets.contains(p) && !base.upgrades.contains(p))
+                .map(|p| (base.repo.clone(), p.clone())),
+        );
+
+        if picks.get(base) != Some(&pick) {
+            picks.insert(base, pick);
+            changed = true;
+        }
+    }
+
+    if changed {
+        picks.save(config)?;
+    }
+
+    Ok(picked)
+}
@@ -1,6 +1,7 @@
 use crate::config::{version, Config};
 use crate::download::cache_info_with_warnings;
 use crate::printtr;
+use crate::upstream::{print_release, upstream_releases};
 use crate::util::repo_aur_pkgs;
 
 use alpm::PackageReason;
@@ -122,5 +123,10 @@ pub async fn stats(config: &Config) -> Result<i32> {
     print_line_separator(config);
     warnings.all(config.color, config.cols);
 
+    let aur_packages = aur_packages.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+    for release in upstream_releases(config, &cache, &aur_packages).await? {
+        print_release(config, &release);
+    }
+
     Ok(0)
 }
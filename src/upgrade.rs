@@ -166,7 +166,7 @@ async fn get_devel_upgrades(config: &Config, print: bool) -> Result<Vec<String>>
         );
     }
 
-    possible_devel_updates(config).await
+    possible_devel_updates(config, true).await
 }
 
 pub async fn net_upgrades<'res>(
@@ -214,7 +214,7 @@ pub async fn get_upgrades<'a, 'b>(
     }
 
     let mut aur_upgrades = upgrades.aur_updates;
-    let pkgbuild_upgrades = upgrades.pkgbuild_updates;
+    let mut pkgbuild_upgrades = upgrades.pkgbuild_updates;
     let mut devel_upgrades =
         filter_devel_updates(config, resolver.get_cache_mut(), &devel_upgrades).await?;
 
@@ -226,8 +226,12 @@ pub async fn get_upgrades<'a, 'b>(
 
     devel_upgrades.sort();
     devel_upgrades.dedup();
-    // TODO better devel pkgbuild
     aur_upgrades.retain(|u| !devel_upgrades.iter().any(|t| t.pkg == u.remote.name));
+    pkgbuild_upgrades.retain(|u| {
+        !devel_upgrades
+            .iter()
+            .any(|t| t.repo.as_deref() == Some(u.repo.as_str()) && t.pkg == u.local.name())
+    });
 
     let mut repo_skip = Vec::new();
     let mut repo_keep = Vec::new();
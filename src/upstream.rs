@@ -0,0 +1,246 @@
+use crate::config::Config;
+use crate::print_error;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::time::Duration;
+
+use alpm::vercmp;
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::join_all;
+use log::debug;
+use raur::Cache;
+use regex::Regex;
+use serde::Deserialize;
+use tokio::process::Command as AsyncCommand;
+use tokio::time::timeout;
+use tr::tr;
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SourceKind {
+    Http,
+    Git,
+    Github,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    source: SourceKind,
+    url: String,
+    regex: Option<String>,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Debug)]
+pub struct Release {
+    pkg: String,
+    packaged: String,
+    upstream: String,
+    aur: bool,
+}
+
+fn load_rules(config: &Config) -> Result<BTreeMap<String, Rule>> {
+    let file = match read_to_string(&config.upstream_path) {
+        Ok(file) => file,
+        _ => return Ok(BTreeMap::new()),
+    };
+
+    let rules = BTreeMap::deserialize(toml::Deserializer::parse(&file)?)
+        .with_context(|| tr!("invalid toml: {}", config.upstream_path.display()))?;
+    Ok(rules)
+}
+
+// strips the epoch and pkgrel so the version can be compared to upstream's
+fn pkgver(version: &str) -> &str {
+    let version = version.split_once(':').map_or(version, |(_, v)| v);
+    version.rsplit_once('-').map_or(version, |(v, _)| v)
+}
+
+async fn fetch(config: &Config, url: &str) -> Result<String> {
+    let path = url
+        .strip_prefix("file://")
+        .or_else(|| url.starts_with('/').then_some(url));
+
+    if let Some(path) = path {
+        debug!("upstream reading {}", path);
+        return read_to_string(path).with_context(|| tr!("failed to read file: {}", path));
+    }
+
+    debug!("upstream fetching {}", url);
+    let resp = config.raur.client().get(url).send().await?;
+    if !resp.status().is_success() {
+        bail!("{}: {}", url, resp.status());
+    }
+    Ok(resp.text().await?)
+}
+
+async fn git_tags(config: &Config, url: &str) -> Result<Vec<String>> {
+    #[cfg(feature = "mock")]
+    let git = "git";
+    #[cfg(not(feature = "mock"))]
+    let git = &config.git_bin;
+
+    let output = AsyncCommand::new(git)
+        .args(&config.git_flags)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(["ls-remote", "--tags", "--refs"])
+        .arg(url)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let tags = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(_, r)| r.strip_prefix("refs/tags/"))
+        .map(|tag| tag.to_string())
+        .collect();
+
+    Ok(tags)
+}
+
+fn github_url(url: &str) -> String {
+    if url.contains("://") || url.starts_with('/') {
+        url.to_string()
+    } else {
+        format!("https://api.github.com/repos/{}/releases", url)
+    }
+}
+
+async fn candidates(config: &Config, rule: &Rule) -> Result<Vec<String>> {
+    let candidates = match rule.source {
+        SourceKind::Http => vec![fetch(config, &rule.url).await?],
+        SourceKind::Git => git_tags(config, &rule.url).await?,
+        SourceKind::Github => {
+            let json = fetch(config, &github_url(&rule.url)).await?;
+            let releases: Vec<GithubRelease> = serde_json::from_str(&json)
+                .with_context(|| tr!("failed to parse releases: {}", rule.url))?;
+            releases
+                .into_iter()
+                .filter(|r| !r.draft && (rule.prerelease || !r.prerelease))
+                .map(|r| r.tag_name)
+                .collect()
+        }
+    };
+
+    Ok(candidates)
+}
+
+async fn latest_version(config: &Config, rule: &Rule) -> Result<String> {
+    let regex = match (&rule.regex, rule.source) {
+        (Some(regex), _) => Regex::new(regex)?,
+        (None, SourceKind::Http) => bail!(tr!("http sources require a regex")),
+        (None, _) => Regex::new(r"^v?(\d.*)$").unwrap(),
+    };
+
+    let candidates = candidates(config, rule).await?;
+
+    // use the first capture group if there is one, otherwise the whole match
+    let version = candidates
+        .iter()
+        .flat_map(|c| regex.captures_iter(c))
+        .filter_map(|c| c.get(1).or_else(|| c.get(0)))
+        .map(|m| m.as_str())
+        .max_by(|a, b| vercmp(*a, *b))
+        .with_context(|| tr!("no versions matched: {}", rule.url))?;
+
+    Ok(version.to_string())
+}
+
+pub async fn upstream_releases(
+    config: &Config,
+    cache: &Cache,
+    pkgs: &[&str],
+) -> Result<Vec<Release>> {
+    let rules = load_rules(config)?;
+    let db = config.alpm.localdb();
+    let mut checked = Vec::new();
+    let mut futures = Vec::new();
+
+    for (pkg, rule) in &rules {
+        if !pkgs.contains(&pkg.as_str()) {
+            continue;
+        }
+        let local = match db.pkg(pkg.as_str()) {
+            Ok(local) => local,
+            Err(_) => continue,
+        };
+
+        let (packaged, aur) = match cache.get(pkg.as_str()) {
+            Some(aur) => (pkgver(&aur.version).to_string(), true),
+            None => (pkgver(local.version().as_str()).to_string(), false),
+        };
+
+        let future = timeout(Duration::from_secs(15), latest_version(config, rule));
+        futures.push(future);
+        checked.push((pkg, packaged, aur));
+    }
+
+    let versions = join_all(futures).await;
+    let mut releases = Vec::new();
+
+    for ((pkg, packaged, aur), version) in checked.into_iter().zip(versions) {
+        let version = match version {
+            Ok(Ok(version)) => version,
+            Ok(Err(err)) => {
+                let err = err.context(tr!("failed to check upstream for {}", pkg));
+                print_error(config.color.error, err);
+                continue;
+            }
+            Err(_) => {
+                let err = anyhow!(tr!("timed out checking upstream for {}", pkg));
+                print_error(config.color.error, err);
+                continue;
+            }
+        };
+
+        debug!("upstream {}: '{}' > '{}'", pkg, version, packaged);
+        if vercmp(version.as_str(), packaged.as_str()) == Ordering::Greater {
+            releases.push(Release {
+                pkg: pkg.to_string(),
+                packaged,
+                upstream: version,
+                aur,
+            });
+        }
+    }
+
+    Ok(releases)
+}
+
+pub fn print_release(config: &Config, release: &Release) {
+    let c = config.color;
+    if release.aur {
+        printtr!(
+            "{arrow} {pkg}: upstream has {upstream}, AUR has {aur}",
+            arrow = c.warning.paint("::"),
+            pkg = c.bold.paint(&release.pkg),
+            upstream = c.upgrade.paint(&release.upstream),
+            aur = release.packaged
+        );
+    } else {
+        printtr!(
+            "{arrow} {pkg}: upstream has {upstream}, installed is {local}",
+            arrow = c.warning.paint("::"),
+            pkg = c.bold.paint(&release.pkg),
+            upstream = c.upgrade.paint(&release.upstream),
+            local = release.packaged
+        );
+    }
+}
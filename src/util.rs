@@ -3,17 +3,20 @@ use crate::repo;
 
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::{stderr, stdin, stdout, BufRead, Write};
+use std::fs::{create_dir_all, read_to_string, rename, File};
+use std::io::{stderr, stdin, stdout, BufRead, ErrorKind, Write};
 use std::mem::take;
 use std::ops::Range;
 use std::os::fd::{AsFd, OwnedFd};
+use std::path::{Path, PathBuf};
 
-use alpm::{Package, PackageReason};
+use alpm::{Db, Package, PackageReason};
 use alpm_utils::depends::{satisfies_dep, satisfies_provide};
 use alpm_utils::{AsTarg, DbListExt, Targ};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use nix::unistd::{dup2_stdin, dup2_stdout};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use tr::tr;
 
 #[derive(Debug)]
@@ -386,6 +389,70 @@ pub fn reopen_stdout<Fd: AsFd>(file: Fd) -> Result<()> {
     Ok(())
 }
 
+/// Runs f on the local db of a fresh handle, as config.alpm is not reloaded
+/// after installing.
+pub fn with_fresh_localdb<T>(config: &Config, f: impl FnOnce(&Db) -> T) -> Result<T> {
+    let alpm = config.new_alpm()?;
+    Ok(f(alpm.localdb()))
+}
+
+/// Writes value to path as toml. The file is written next to path first and
+/// then renamed over it so it is never left half written.
+pub fn save_toml<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let toml =
+        toml::to_string(value).with_context(|| tr!("failed to serialize: {}", path.display()))?;
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = File::create(&temp)
+        .with_context(|| tr!("failed to create temporary file: {}", temp.display()))?;
+    file.write_all(toml.as_bytes())
+        .with_context(|| tr!("failed to write to temporary file: {}", temp.display()))?;
+    drop(file);
+
+    rename(&temp, path).with_context(|| {
+        tr!(
+            "failed to rename '{temp}' to '{path}'",
+            temp = temp.display(),
+            path = path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Reads the toml file at path.
+pub fn load_toml<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file =
+        read_to_string(path).with_context(|| tr!("failed to read file: {}", path.display()))?;
+    parse_toml(&file, path)
+}
+
+fn parse_toml<T: DeserializeOwned>(file: &str, path: &Path) -> Result<T> {
+    let value = T::deserialize(toml::Deserializer::parse(file)?)
+        .with_context(|| tr!("invalid toml: {}", path.display()))?;
+    Ok(value)
+}
+
+/// Saves value to the file called name in the state directory.
+pub fn save_state<T: Serialize>(state_dir: &Path, name: &str, value: &T) -> Result<()> {
+    create_dir_all(state_dir)
+        .with_context(|| tr!("failed to create state directory: {}", state_dir.display()))?;
+    save_toml(&state_dir.join(name), value)
+}
+
+/// Loads the file called name from the state directory, None if it was never saved.
+pub fn load_state<T: DeserializeOwned>(state_dir: &Path, name: &str) -> Result<Option<T>> {
+    let path = state_dir.join(name);
+    let file = match read_to_string(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| tr!("failed to read file: {}", path.display())),
+    };
+    parse_toml(&file, &path).map(Some)
+}
+
 pub fn is_arch_repo(name: &str) -> bool {
     matches!(
         name,
@@ -10,6 +10,11 @@ use std::process::Command;
 use tempfile::TempDir;
 
 async fn run(run_args: &[&str], repo: bool) -> Result<(TempDir, i32)> {
+    let (tmp, ret, _) = run_paru(run_args, repo, false).await?;
+    Ok((tmp, ret))
+}
+
+async fn run_paru(run_args: &[&str], repo: bool, capture: bool) -> Result<(TempDir, i32, String)> {
     let tmp = TempDir::new().unwrap();
     let dir = tmp.path();
     let testdata = Path::new(&var("CARGO_MANIFEST_DIR").unwrap()).join("testdata");
@@ -113,6 +118,9 @@ async fn run(run_args: &[&str], repo: bool) -> Result<(TempDir, i32)> {
     let develfile = dir.join("devel.toml");
     let develfile = develfile.to_str();
 
+    let statedir = dir.join("state");
+    let statedir = statedir.to_str();
+
     let mut args = vec![
         "--root=/var/empty",
         "--dbonly",
@@ -126,6 +134,8 @@ async fn run(run_args: &[&str], repo: bool) -> Result<(TempDir, i32)> {
         pconf.unwrap(),
         "--develfile",
         develfile.unwrap(),
+        "--statedir",
+        statedir.unwrap(),
         "--makepkgconf",
         mconf.unwrap(),
     ];
@@ -177,7 +187,15 @@ async fn run(run_args: &[&str], repo: bool) -> Result<(TempDir, i32)> {
     }
 
     args.extend(run_args);
-    let ret = paru::run(&args).await;
+    let (ret, stdout) = if capture {
+        let output = Command::new(env!("CARGO_BIN_EXE_paru"))
+            .args(&args)
+            .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        (output.status.code().unwrap_or(1), stdout)
+    } else {
+        (paru::run(&args).await, String::new())
+    };
 
     for pkg in std::fs::read_dir(dir.join("cache/pkg"))? {
         let path = pkg?.path();
@@ -188,7 +206,7 @@ async fn run(run_args: &[&str], repo: bool) -> Result<(TempDir, i32)> {
         }
     }
 
-    Ok((tmp, ret))
+    Ok((tmp, ret, stdout))
 }
 
 pub async fn run_normal(run_args: &[&str]) -> Result<(TempDir, i32)> {
@@ -218,6 +236,18 @@ pub async fn run_repo_chroot(run_args: &[&str]) -> Result<(TempDir, i32)> {
     run(&args, true).await
 }
 
+/// Runs the paru binary instead of calling into the library so stdout can be
+/// checked.
+pub async fn output(run_args: &[&str]) -> Result<(TempDir, i32, String)> {
+    run_paru(run_args, false, true).await
+}
+
+/// Writes a toml file into a state dir, e.g. one passed with --statedir.
+pub fn write_state(dir: &Path, name: &str, toml: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join(name), toml).unwrap();
+}
+
 pub fn alpm(tmp: &TempDir) -> Result<Alpm> {
     let alpm = Alpm::new("/var/empty", tmp.path().join("db").to_str().unwrap())?;
     if tmp.path().join("localrepo").exists() {
@@ -199,3 +199,171 @@ async fn devel() {
     let a = db.pkg("devel").unwrap();
     assert_eq!(a.version().as_str(), "2-1");
 }
+
+async fn devel_tag(tag: &str, commit: &str, args: &[&str]) -> String {
+    let dir = tempfile::TempDir::new().unwrap();
+    let develfile = dir.path().join("devel.toml");
+    std::fs::write(
+        &develfile,
+        format!(
+            "[[devel]]\nurl = \"testdata/git-repo\"\ncommit = \"{}\"\ntag = \"{}\"\n",
+            commit, tag
+        ),
+    )
+    .unwrap();
+
+    let mut args = args.to_vec();
+    args.extend([
+        "-Sua",
+        "--devel",
+        "--develfile",
+        develfile.to_str().unwrap(),
+    ]);
+    let (tmp, ret) = run(&args).await.unwrap();
+    assert_eq!(ret, 0);
+
+    let alpm = alpm(&tmp).unwrap();
+    let db = alpm.localdb();
+    db.pkg("devel").unwrap().version().to_string()
+}
+
+#[tokio::test]
+async fn devel_tag_update() {
+    let version = devel_tag("v1.0", "deadbeef", &["--develtags"]).await;
+    assert_eq!(version, "2-1");
+}
+
+#[tokio::test]
+async fn devel_tag_newest() {
+    let version = devel_tag("v2.0", "deadbeef", &["--develtags"]).await;
+    assert_eq!(version, "1-1");
+}
+
+#[tokio::test]
+async fn devel_tag_pinned() {
+    let commit = "2545915d2a057d068a8585ebfc0592ba543189a7";
+    let version = devel_tag("v1.0", commit, &["--develtags"]).await;
+    assert_eq!(version, "1-1");
+}
+
+#[tokio::test]
+async fn devel_tag_disabled() {
+    let version = devel_tag("v1.0", "deadbeef", &[]).await;
+    assert_eq!(version, "1-1");
+}
+
+#[tokio::test]
+async fn gendb_from_clones_tag() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let repo = std::fs::canonicalize("testdata/git-repo").unwrap();
+    let clone = dir.path().join("clone/devel");
+    std::fs::create_dir_all(clone.join("src")).unwrap();
+    std::fs::write(
+        clone.join(".SRCINFO"),
+        format!(
+            "pkgbase = devel\n\tpkgver = 1\n\tpkgrel = 1\n\tarch = any\n\t\
+             source = git+file://{}#tag=v1.0\n\npkgname = devel\n",
+            repo.display()
+        ),
+    )
+    .unwrap();
+    let status = std::process::Command::new("git")
+        .args(["clone", "-q", "--branch", "v1.0"])
+        .arg(&repo)
+        .arg(clone.join("src/git-repo"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let develfile = dir.path().join("devel.toml");
+    let (_tmp, ret) = run(&[
+        "--gendb",
+        "--fromclones",
+        "--develtags",
+        "--clonedir",
+        dir.path().join("clone").to_str().unwrap(),
+        "--develfile",
+        develfile.to_str().unwrap(),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(ret, 0);
+
+    let devel = std::fs::read_to_string(develfile).unwrap();
+    assert!(devel.contains("tag = \"v1.0\""));
+    assert!(devel.contains("commit = \"2545915d2a057d068a8585ebfc0592ba543189a7\""));
+}
+
+#[tokio::test]
+async fn print_plan_refresh() {
+    let (_tmp, ret) = run(&["-Sy", "--print-plan", "pacaur"]).await.unwrap();
+    assert_eq!(ret, 1);
+}
+
+async fn apply(manifest: &str) -> (i32, String) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("manifest.toml");
+    std::fs::write(&path, manifest).unwrap();
+
+    let (_tmp, ret, stdout) = output(&["--apply", path.to_str().unwrap(), "--print-plan"])
+        .await
+        .unwrap();
+    (ret, stdout)
+}
+
+#[tokio::test]
+async fn apply_matches() {
+    let (ret, stdout) = apply("repo = [\"pacman>=5\"]\naur = [\"polybar\"]\n").await;
+    assert_eq!(ret, 0);
+    assert!(stdout.is_empty());
+}
+
+#[tokio::test]
+async fn apply_too_old() {
+    let (ret, stdout) = apply("aur = [\"polybar>=3\"]\n").await;
+    assert_eq!(ret, 0);
+    assert!(stdout.contains("polybar"));
+}
+
+#[tokio::test]
+async fn apply_unsatisfiable() {
+    let (ret, _) = apply("repo = [\"pacman>=99\"]\n").await;
+    assert_eq!(ret, 1);
+}
+
+#[tokio::test]
+async fn split_pick() {
+    let (tmp, ret) = run(&["-S", "split-a"]).await.unwrap();
+    assert_eq!(ret, 0);
+    let alpm = alpm(&tmp).unwrap();
+
+    let db = alpm.localdb();
+    assert!(db.pkg("split-a").is_ok());
+    assert!(db.pkg("split-b").is_err());
+    assert!(db.pkg("other").is_err());
+
+    let picks = std::fs::read_to_string(tmp.path().join("state/splits.toml")).unwrap();
+    assert!(picks.contains("[aur]"));
+    assert!(picks.contains("split = [\"split-a\"]"));
+}
+
+#[tokio::test]
+async fn split_saved() {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_state(
+        dir.path(),
+        "splits.toml",
+        "[aur]\nsplit = [\"split-a\", \"other\"]\n",
+    );
+
+    let (tmp, ret) = run(&["-S", "split-a", "--statedir", dir.path().to_str().unwrap()])
+        .await
+        .unwrap();
+    assert_eq!(ret, 0);
+    let alpm = alpm(&tmp).unwrap();
+
+    let db = alpm.localdb();
+    assert!(db.pkg("split-a").is_ok());
+    assert!(db.pkg("split-b").is_err());
+    assert!(db.pkg("other").is_ok());
+}
@@ -28,3 +28,296 @@ mod repo_chroot {
     use crate::common::run_repo_chroot as run;
     include!("common/tests.rs");
 }
+
+mod state {
+    use crate::common::run_normal as run;
+    use crate::common::*;
+    use alpm::PackageReason;
+    use std::path::Path;
+
+    fn write_journal(state: &Path, version: &str) {
+        let alpm = alpm::Alpm::new("/var/empty", "testdata/db").unwrap();
+        let mut journal = format!(
+            "install_targets = true\nremove_make = []\n\n[[bases]]\npkgbase = \"pacaur\"\n\
+             version = \"{}\"\nbuild = true\nbuilt = false\ninstalled = false\n\n\
+             [[bases.pkgs]]\nname = \"pacaur\"\nmake = false\ntarget = true\n\n[local]\n",
+            version
+        );
+        for pkg in alpm.localdb().pkgs() {
+            journal.push_str(&format!("\"{}\" = \"{}\"\n", pkg.name(), pkg.version()));
+        }
+
+        write_state(state, "transaction.toml", &journal);
+    }
+
+    #[tokio::test]
+    async fn resume_nothing() {
+        let (_tmp, ret) = run(&["--resume"]).await.unwrap();
+        assert_eq!(ret, 1);
+    }
+
+    #[tokio::test]
+    async fn resume_changed_version() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_journal(dir.path(), "0.1-1");
+
+        let (tmp, ret) = run(&["--resume", "--statedir", dir.path().to_str().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(ret, 1);
+        assert!(dir.path().join("transaction.toml").exists());
+
+        let alpm = alpm(&tmp).unwrap();
+        assert!(alpm.localdb().pkg("pacaur").is_err());
+    }
+
+    #[tokio::test]
+    async fn resume_abort() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_journal(dir.path(), "0.1-1");
+
+        let (_tmp, ret) = run(&["--resume=abort", "--statedir", dir.path().to_str().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(ret, 0);
+        assert!(!dir.path().join("transaction.toml").exists());
+    }
+
+    const FAILED: &str = "bases = [\"auracle-git\"]\ntargets = [\"pacaur\"]\n";
+
+    #[tokio::test]
+    async fn retry_failed_nothing() {
+        let (_tmp, ret) = run(&["--retry-failed"]).await.unwrap();
+        assert_eq!(ret, 1);
+    }
+
+    #[tokio::test]
+    async fn retry_failed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(dir.path(), "failed.toml", FAILED);
+
+        let (tmp, ret) = run(&["--retry-failed", "--statedir", dir.path().to_str().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(ret, 0);
+        assert!(!dir.path().join("failed.toml").exists());
+
+        let alpm = alpm(&tmp).unwrap();
+        let db = alpm.localdb();
+        assert_eq!(db.pkg("pacaur").unwrap().reason(), PackageReason::Explicit);
+        assert_eq!(
+            db.pkg("auracle-git").unwrap().reason(),
+            PackageReason::Depend
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_failed_cleared() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(dir.path(), "failed.toml", FAILED);
+
+        let (_tmp, ret) = run(&["-S", "polybar", "--statedir", dir.path().to_str().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(ret, 0);
+        assert!(!dir.path().join("failed.toml").exists());
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=paru", "-c", "user.email=paru@localhost"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    // a clone of polybar with the installed version 1.0.0 committed before 3.5.6
+    fn polybar_clone(dir: &Path, old: bool) -> Option<String> {
+        let clone = dir.join("clone/polybar");
+        std::fs::create_dir_all(&clone).unwrap();
+        let srcinfo = std::fs::read_to_string("testdata/clone/polybar/.SRCINFO").unwrap();
+        git(&clone, &["init", "-q"]);
+
+        let mut commit = None;
+        if old {
+            std::fs::write(clone.join(".SRCINFO"), srcinfo.replace("3.5.6", "1.0.0")).unwrap();
+            git(&clone, &["add", ".SRCINFO"]);
+            git(&clone, &["commit", "-q", "-m", "1.0.0"]);
+            commit = Some(git(&clone, &["rev-parse", "HEAD"]));
+        }
+
+        std::fs::write(clone.join(".SRCINFO"), srcinfo).unwrap();
+        git(&clone, &["add", ".SRCINFO"]);
+        git(&clone, &["commit", "-q", "-m", "3.5.6"]);
+        commit
+    }
+
+    #[tokio::test]
+    async fn writelock() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let commit = polybar_clone(dir.path(), true).unwrap();
+        let lockfile = dir.path().join("paru.lock");
+
+        let (_tmp, ret) = run(&[
+            "--writelock",
+            lockfile.to_str().unwrap(),
+            "--git",
+            "git",
+            "--clonedir",
+            dir.path().join("clone").to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(ret, 0);
+
+        let lock = std::fs::read_to_string(lockfile).unwrap();
+        assert!(lock.contains("pkgbase = \"polybar\""));
+        assert!(lock.contains("version = \"1.0.0-1\""));
+        assert!(lock.contains(&format!("commit = \"{}\"", commit)));
+    }
+
+    #[tokio::test]
+    async fn writelock_newer_clone() {
+        let dir = tempfile::TempDir::new().unwrap();
+        polybar_clone(dir.path(), false);
+        let lockfile = dir.path().join("paru.lock");
+
+        let (_tmp, ret) = run(&[
+            "--writelock",
+            lockfile.to_str().unwrap(),
+            "--git",
+            "git",
+            "--clonedir",
+            dir.path().join("clone").to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(ret, 0);
+
+        let lock = std::fs::read_to_string(lockfile).unwrap();
+        assert!(!lock.contains("polybar"));
+    }
+
+    // BuildUser only applies when paru is run as root
+    #[tokio::test]
+    async fn build_user_not_root() {
+        let (tmp, ret) = run(&["-S", "pacaur", "--builduser", "nobody"])
+            .await
+            .unwrap();
+        assert_eq!(ret, 0);
+        let alpm = alpm(&tmp).unwrap();
+
+        let db = alpm.localdb();
+        assert!(db.pkg("pacaur").is_ok());
+        assert!(db.pkg("auracle-git").is_ok());
+    }
+
+    #[tokio::test]
+    async fn providers_list() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(dir.path(), "providers.toml", "sh = \"bash\"\n");
+
+        let (_tmp, ret, stdout) =
+            output(&["--providers", "--statedir", dir.path().to_str().unwrap()])
+                .await
+                .unwrap();
+        assert_eq!(ret, 0);
+        assert!(stdout.contains("sh bash"));
+    }
+
+    #[tokio::test]
+    async fn providers_reset() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(
+            dir.path(),
+            "providers.toml",
+            "sh = \"bash\"\njava-environment = \"jdk-openjdk\"\n",
+        );
+
+        let (_tmp, ret) = run(&[
+            "--resetproviders",
+            "sh",
+            "--statedir",
+            dir.path().to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(ret, 0);
+
+        let providers = std::fs::read_to_string(dir.path().join("providers.toml")).unwrap();
+        assert!(!providers.contains("sh"));
+        assert!(providers.contains("java-environment = \"jdk-openjdk\""));
+    }
+
+    #[tokio::test]
+    async fn providers_reset_unknown() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(dir.path(), "providers.toml", "sh = \"bash\"\n");
+
+        let (_tmp, ret) = run(&[
+            "--resetproviders",
+            "awk",
+            "--statedir",
+            dir.path().to_str().unwrap(),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(ret, 1);
+    }
+
+    // the saved providers are only read when there is a provider to pick
+    #[tokio::test]
+    async fn providers_invalid() {
+        let dir = tempfile::TempDir::new().unwrap();
+        write_state(dir.path(), "providers.toml", "not toml");
+
+        let (tmp, ret) = run(&["-S", "pacaur", "--statedir", dir.path().to_str().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(ret, 0);
+        let alpm = alpm(&tmp).unwrap();
+        assert!(alpm.localdb().pkg("pacaur").is_ok());
+    }
+}
+
+mod upstream {
+    use crate::common::*;
+
+    async fn upstream(rule: &str) -> String {
+        let dir = tempfile::TempDir::new().unwrap();
+        let upstreamfile = dir.path().join("upstream.toml");
+        std::fs::write(
+            &upstreamfile,
+            rule.replace("DIR", dir.path().to_str().unwrap()),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("releases.html"),
+            "<a href=\"polybar-3.5.6.tar.gz\"></a>\n<a href=\"polybar-3.6.0.tar.gz\"></a>\n",
+        )
+        .unwrap();
+
+        let args = ["-Qua", "--upstreamfile", upstreamfile.to_str().unwrap()];
+        let (_tmp, _ret, stdout) = output(&args).await.unwrap();
+        stdout
+    }
+
+    #[tokio::test]
+    async fn http() {
+        let stdout = upstream(
+            "[polybar]\nsource = \"http\"\nurl = \"DIR/releases.html\"\n\
+             regex = 'polybar-([0-9.]+)\\.tar'\n",
+        )
+        .await;
+        assert!(stdout.contains("polybar: upstream has 3.6.0, AUR has 3.5.6"));
+    }
+
+    #[tokio::test]
+    async fn git_older() {
+        let stdout = upstream("[polybar]\nsource = \"git\"\nurl = \"testdata/git-repo\"\n").await;
+        assert!(!stdout.contains("upstream has"));
+    }
+}